@@ -0,0 +1,92 @@
+// src/anonymize.rs
+
+/// Consistently replaces project-specific terms (company names, product codenames, ...)
+/// with neutral placeholders.
+///
+/// Matching ignores ASCII case, also inside longer identifiers, and the replacement keeps the
+/// case style of each match: with the terms `["Acme"]`, `AcmeClient` becomes `Anon1Client`,
+/// `acme_client` becomes `anon1_client` and `ACME_URL` becomes `ANON1_URL`.
+pub struct Anonymizer {
+    /// Terms and their placeholders, in configuration order.
+    replacements: Vec<(String, String)>,
+    /// Indices into `replacements`, longest terms first so they win over their substrings.
+    match_order: Vec<usize>,
+}
+
+impl Anonymizer {
+    pub fn new(terms: &[String]) -> Self {
+        let replacements: Vec<(String, String)> = terms
+            .iter()
+            .filter(|term| !term.is_empty())
+            .enumerate()
+            .map(|(i, term)| (term.clone(), format!("Anon{}", i + 1)))
+            .collect();
+        let mut match_order: Vec<usize> = (0..replacements.len()).collect();
+        match_order.sort_by_key(|&i| std::cmp::Reverse(replacements[i].0.len()));
+        Anonymizer { replacements, match_order }
+    }
+
+    /// Returns the terms with their placeholders, in configuration order.
+    pub fn mapping(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.replacements
+            .iter()
+            .map(|(term, placeholder)| (term.as_str(), placeholder.as_str()))
+    }
+
+    /// Replaces every occurrence of the configured terms in `text`.
+    ///
+    /// # Returns
+    /// The anonymized text and the number of replacements made.
+    pub fn apply(&self, text: &str) -> (String, usize) {
+        let mut result = String::with_capacity(text.len());
+        let mut count = 0;
+        let mut rest = text;
+
+        'scan: while !rest.is_empty() {
+            for (term, placeholder) in self.match_order.iter().map(|&i| &self.replacements[i]) {
+                if let Some(matched) = rest.get(..term.len()).filter(|head| head.eq_ignore_ascii_case(term)) {
+                    result.push_str(&match_case(matched, placeholder));
+                    rest = &rest[term.len()..];
+                    count += 1;
+                    continue 'scan;
+                }
+            }
+            let next = rest.chars().next().unwrap();
+            result.push(next);
+            rest = &rest[next.len_utf8()..];
+        }
+        (result, count)
+    }
+}
+
+/// Gives `placeholder` the case style of `matched`: all upper, all lower or as is.
+fn match_case(matched: &str, placeholder: &str) -> String {
+    let has_letters = matched.chars().any(|c| c.is_alphabetic());
+    if has_letters && !matched.chars().any(|c| c.is_lowercase()) {
+        placeholder.to_uppercase()
+    } else if has_letters && !matched.chars().any(|c| c.is_uppercase()) {
+        placeholder.to_lowercase()
+    } else {
+        placeholder.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let anonymizer = Anonymizer::new(&["Acme".to_string(), "Roadrunner".to_string(), "Acme Corp".to_string()]);
+
+        let (text, count) = anonymizer.apply("struct AcmeClient; let acme_url = ACME_URL; // Acme Corp's Roadrunner");
+        assert_eq!(text, "struct Anon1Client; let anon1_url = ANON1_URL; // Anon3's Anon2");
+        assert_eq!(count, 5);
+
+        let (text, count) = anonymizer.apply("nothing to hide, café");
+        assert_eq!(text, "nothing to hide, café");
+        assert_eq!(count, 0);
+
+        assert_eq!(anonymizer.mapping().collect::<Vec<_>>(), vec![("Acme", "Anon1"), ("Roadrunner", "Anon2"), ("Acme Corp", "Anon3")]);
+    }
+}
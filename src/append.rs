@@ -0,0 +1,138 @@
+// src/append.rs
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::atomic::AtomicFile;
+use crate::config::Config;
+use crate::manifest::{self, IncludedFile, Manifest};
+use crate::pipeline;
+use crate::{containment, generate_tree_output, write_source_file, Root, Walk};
+
+/// Adds a labeled section to an existing prompt file and records its files in the prompt's
+/// manifest.
+///
+/// Directories are rendered as the content of their files matching the allowed extensions
+/// followed by their subtree, under a `Root:` header; files, such as logs, are included whatever
+/// their extension. The section
+/// is inserted before the goal line when the prompt has one, appended at the end otherwise.
+///
+/// # Arguments
+/// * `prompt_path` - The prompt file to extend.
+/// * `label` - Title of the new section.
+/// * `paths` - Directories and files to include, relative to `project_dir` or absolute.
+/// * `allow_outside_root` - Whether symbolic links leaving the project are followed.
+///
+/// # Returns
+/// The number of files added, the files whose secrets were redacted, and the other warnings,
+/// such as omitted data blobs or links skipped for leaving the project.
+pub fn append_section(
+    config: &Config,
+    project_dir: &Path,
+    prompt_path: &Path,
+    label: &str,
+    paths: &[impl AsRef<Path>],
+    allow_outside_root: bool,
+) -> io::Result<(usize, Vec<pipeline::Redaction>, Vec<String>)> {
+    let prompt = fs::read_to_string(prompt_path)?;
+    let strings = config.strings();
+
+    let mut section = format!("{}: {}\n", strings.section, label).into_bytes();
+    let mut walk = Walk {
+        config,
+        project_dir,
+        containment: (!allow_outside_root).then(|| containment(project_dir)),
+        deadline: None,
+        timed_out: false,
+        entries: 0,
+        include_contents: true,
+        changeset: None,
+        included: Vec::new(),
+        exclusions: None,
+        ignore: None,
+        output_dir: config.output_dir(project_dir).canonicalize().ok(),
+        images: None,
+        redactions: Vec::new(),
+        warnings: Vec::new(),
+        listings: HashMap::new(),
+    };
+    for path in paths {
+        let path = project_dir.join(path);
+        let display_path = path.strip_prefix(project_dir).unwrap_or(&path).display().to_string();
+        if path.is_dir() {
+            let root = Root {
+                label: Some(display_path),
+                path,
+            };
+            let tree_output = generate_tree_output(&root, &mut walk, &mut section);
+            walk.check_entries()?;
+            section.extend_from_slice(tree_output.as_bytes());
+        } else {
+            let findings = write_source_file(&mut section, config, project_dir, &path, &display_path)?;
+            walk.record(&display_path, findings);
+            walk.included.push(IncludedFile {
+                path: display_path,
+                source: path,
+            });
+        }
+    }
+    let section = String::from_utf8_lossy(&section);
+
+    // Keep the goal last, where the model expects it
+    let goal_line = format!("{}: ", strings.specific_goal);
+    let updated = match prompt.rfind(&format!("\n{}", goal_line)) {
+        Some(position) => format!("{}{}{}", &prompt[..=position], section, &prompt[position + 1..]),
+        None if prompt.starts_with(&goal_line) => format!("{}{}", section, prompt),
+        None => format!("{}{}", prompt, section),
+    };
+    // Replace the prompt only once it is fully written, so that a failure leaves it whole
+    let mut prompt_file = AtomicFile::create(prompt_path)?;
+    prompt_file.write_all(updated.as_bytes())?;
+    prompt_file.commit()?;
+
+    let manifest_path = manifest::manifest_path(prompt_path);
+    let mut manifest = match fs::read_to_string(&manifest_path) {
+        Ok(content) => serde_json::from_str(&content)?,
+        Err(_) => Manifest {
+            goal: String::new(),
+            files: Vec::new(),
+        },
+    };
+    let added = walk.included.len();
+    manifest.files.extend(walk.included);
+    manifest::save_manifest(&manifest, prompt_path)?;
+    Ok((added, walk.redactions, walk.warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_append_section() {
+        let project_dir = TempDir::new("append");
+        fs::create_dir_all(project_dir.join("net")).unwrap();
+        fs::write(project_dir.join("net/client.rs"), "fn connect() {}\n").unwrap();
+        fs::write(project_dir.join("error.log"), "panicked at src/main.rs:3\n").unwrap();
+        let prompt_path = project_dir.join("prompt.txt");
+        fs::write(&prompt_path, "Intro\nSpecific Goal: Fix the crash\n").unwrap();
+        let config = Config {
+            allowed_extensions: vec!["rs".to_string()],
+            ..Default::default()
+        };
+
+        let (added, _, _) = append_section(&config, &project_dir, &prompt_path, "Crash", &["net", "error.log"], false).unwrap();
+        assert_eq!(added, 2);
+        assert_eq!(
+            fs::read_to_string(&prompt_path).unwrap(),
+            "Intro\nSection: Crash\nFile: net/client.rs\n```\nfn connect() {}\n```\nRoot: net\n└── client.rs\n\
+             File: error.log\n```\npanicked at src/main.rs:3\n```\nSpecific Goal: Fix the crash\n"
+        );
+        let manifest: Manifest = serde_json::from_str(&fs::read_to_string(manifest::manifest_path(&prompt_path)).unwrap()).unwrap();
+        let paths: Vec<&str> = manifest.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["net/client.rs", "error.log"]);
+    }
+}
@@ -0,0 +1,87 @@
+// src/atomic.rs
+
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// A file written under a temporary name next to its destination, `name.<pid>.tmp`, and renamed
+/// over it once complete, so that a crash or a concurrent run never leaves a truncated file.
+/// Writes are buffered. The temporary file is removed when dropped without being committed, e.g.
+/// on a write error.
+pub struct AtomicFile {
+    file: BufWriter<fs::File>,
+    temp_path: PathBuf,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    /// Creates the temporary file of `path`, in the same directory so that the rename stays on
+    /// the same file system.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file_name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the output path has no file name"))?;
+        let temp_path = path.with_file_name(format!("{}.{}.tmp", file_name.to_string_lossy(), std::process::id()));
+        let file = BufWriter::new(fs::File::create(&temp_path)?);
+        Ok(AtomicFile {
+            file,
+            temp_path,
+            path: path.to_path_buf(),
+            committed: false,
+        })
+    }
+
+    /// Flushes the content to disk and moves it to the destination path, replacing any file
+    /// already there.
+    pub fn commit(mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.get_ref().sync_all()?;
+        fs::rename(&self.temp_path, &self.path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_atomic_file() {
+        let dir = TempDir::new("atomic");
+        let path = dir.join("app.txt");
+        fs::write(&path, "previous prompt").unwrap();
+
+        // The destination keeps its content until the new one is complete
+        let mut file = AtomicFile::create(&path).unwrap();
+        write!(file, "new prompt").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "previous prompt");
+        file.commit().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new prompt");
+
+        // Abandoned files leave no temporary file behind
+        let mut file = AtomicFile::create(&path).unwrap();
+        write!(file, "partial").unwrap();
+        drop(file);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new prompt");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,104 @@
+// src/budget.rs
+
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::manifest::IncludedFile;
+use crate::write_source_file;
+
+/// Number of files listed when a prompt exceeds its budget.
+const LARGEST_FILES: usize = 10;
+
+/// Tokens taken by one file of a prompt.
+#[derive(Debug, PartialEq)]
+pub struct FileTokens<'a> {
+    pub file: &'a IncludedFile,
+    /// Tokens of the file section: header, fences and cleaned content.
+    pub tokens: usize,
+}
+
+/// Counts the tokens of each included file's section with the configured tokenizer, largest first.
+pub fn file_tokens<'a>(config: &Config, project_dir: &Path, files: &'a [IncludedFile]) -> io::Result<Vec<FileTokens<'a>>> {
+    let mut sizes = Vec::with_capacity(files.len());
+    for file in files {
+        let mut section = Vec::new();
+        write_source_file(&mut section, config, project_dir, &file.source, &file.path)?;
+        sizes.push(FileTokens {
+            file,
+            tokens: config.tokenizer.count(&String::from_utf8_lossy(&section)),
+        });
+    }
+    sizes.sort_by(|a, b| b.tokens.cmp(&a.tokens).then_with(|| a.file.path.cmp(&b.file.path)));
+    Ok(sizes)
+}
+
+/// Returns the largest files whose removal saves at least `excess` tokens, or every file when
+/// that is not enough.
+///
+/// # Arguments
+/// * `sizes` - File sizes, largest first, as returned by [`file_tokens`].
+/// * `excess` - Number of tokens over the budget.
+pub fn files_to_drop<'a, 'b>(sizes: &'b [FileTokens<'a>], excess: usize) -> &'b [FileTokens<'a>] {
+    let mut saved = 0;
+    let count = sizes
+        .iter()
+        .take_while(|size| {
+            let needed = saved < excess;
+            saved += size.tokens;
+            needed
+        })
+        .count();
+    &sizes[..count]
+}
+
+/// Describes a prompt over its budget and lists the files taking the most tokens.
+pub fn report(prompt_tokens: usize, max_tokens: usize, sizes: &[FileTokens]) -> String {
+    let mut report = format!(
+        "The prompt takes {} tokens, over the max_tokens budget of {} by {}.\n",
+        prompt_tokens,
+        max_tokens,
+        prompt_tokens - max_tokens
+    );
+    if !sizes.is_empty() {
+        report.push_str("Largest files:\n");
+        for size in sizes.iter().take(LARGEST_FILES) {
+            let _ = writeln!(report, "  {}: {} tokens", size.file.path, size.tokens);
+        }
+    }
+    report.push_str("Deny some of them, raise max_tokens, or set over_budget = \"trim\" to leave the largest files out.\n");
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_files_to_drop() {
+        let project_dir = TempDir::new("budget");
+        let files: Vec<IncludedFile> = [("small.rs", 10), ("large.rs", 400), ("medium.rs", 100)]
+            .iter()
+            .map(|(name, length)| {
+                fs::write(project_dir.join(name), "x".repeat(*length)).unwrap();
+                IncludedFile {
+                    path: name.to_string(),
+                    source: project_dir.join(name),
+                }
+            })
+            .collect();
+        let config = Config::default();
+
+        let sizes = file_tokens(&config, &project_dir, &files).unwrap();
+        let paths: Vec<&str> = sizes.iter().map(|size| size.file.path.as_str()).collect();
+        assert_eq!(paths, vec!["large.rs", "medium.rs", "small.rs"]);
+        assert_eq!(files_to_drop(&sizes, 50).len(), 1);
+        assert_eq!(files_to_drop(&sizes, sizes[0].tokens + 1).len(), 2);
+        assert_eq!(files_to_drop(&sizes, 10_000).len(), 3);
+        assert!(report(1200, 1000, &sizes).contains("over the max_tokens budget of 1000 by 200.\nLargest files:\n  large.rs:"));
+    }
+}
@@ -0,0 +1,265 @@
+// src/builder.rs
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::manifest::IncludedFile;
+use crate::generate::Generation;
+use crate::{containment, generate_tree_output, project_roots, write_prompt, BuildOptions, Walk};
+
+/// Lists the files of a project that a prompt would include, without reading them.
+#[derive(Debug, Clone)]
+pub struct FileCollector<'a> {
+    config: &'a Config,
+    project_dir: PathBuf,
+    allow_outside_root: bool,
+    timeout: Option<Duration>,
+}
+
+impl<'a> FileCollector<'a> {
+    /// A collector for the project at `project_dir`, filtered by `config`.
+    pub fn new(config: &'a Config, project_dir: impl Into<PathBuf>) -> Self {
+        FileCollector {
+            config,
+            project_dir: project_dir.into(),
+            allow_outside_root: false,
+            timeout: None,
+        }
+    }
+
+    /// Follows roots and symbolic links resolving outside the project directory.
+    pub fn with_allow_outside_root(mut self, allow_outside_root: bool) -> Self {
+        self.allow_outside_root = allow_outside_root;
+        self
+    }
+
+    /// Stops walking after `timeout`, returning the files found so far.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Walks the project roots and returns the files matching the allowed extensions outside
+    /// the denied directories, in prompt order.
+    pub fn collect(&self) -> Vec<IncludedFile> {
+        let project_dir = self.project_dir.as_path();
+        let containment = (!self.allow_outside_root).then(|| containment(project_dir));
+        let roots = project_roots(self.config, project_dir, containment.as_deref(), &mut Vec::new());
+        let mut walk = Walk {
+            config: self.config,
+            project_dir,
+            containment,
+            deadline: self.timeout.filter(|_| !self.config.deterministic).map(|timeout| Instant::now() + timeout),
+            timed_out: false,
+            entries: 0,
+            include_contents: false,
+            changeset: None,
+            included: Vec::new(),
+            exclusions: None,
+            ignore: None,
+            output_dir: self.config.output_dir(project_dir).canonicalize().ok(),
+            images: None,
+            redactions: Vec::new(),
+            warnings: Vec::new(),
+            listings: HashMap::new(),
+        };
+        for root in &roots {
+            generate_tree_output(root, &mut walk, &mut io::sink());
+        }
+        walk.included
+    }
+}
+
+/// Builds prompts in memory, for tools embedding prompt generation such as editor plugins.
+///
+/// Nothing is written to disk and the project history is left untouched; see
+/// [`generate`](crate::generate) to write prompt files.
+#[derive(Debug, Clone)]
+pub struct PromptBuilder {
+    config: Config,
+    project_dir: PathBuf,
+    allow_outside_root: bool,
+    timeout: Option<Duration>,
+}
+
+/// A prompt built by [`PromptBuilder`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Prompt {
+    /// The full prompt, ending with the goal.
+    pub text: String,
+    /// The files whose content is included.
+    pub files: Vec<IncludedFile>,
+    /// Estimated number of tokens of the prompt.
+    pub tokens: usize,
+    /// Whether generation was cut short by the timeout.
+    pub partial: bool,
+    /// Warnings about the prompt, such as redacted secrets or files left out to fit `max_tokens`.
+    pub warnings: Vec<String>,
+}
+
+impl PromptBuilder {
+    /// A builder for the project at `project_dir` with `config`.
+    pub fn new(config: Config, project_dir: impl Into<PathBuf>) -> Self {
+        PromptBuilder {
+            config,
+            project_dir: project_dir.into(),
+            allow_outside_root: false,
+            timeout: None,
+        }
+    }
+
+    /// Follows roots and symbolic links resolving outside the project directory.
+    pub fn with_allow_outside_root(mut self, allow_outside_root: bool) -> Self {
+        self.allow_outside_root = allow_outside_root;
+        self
+    }
+
+    /// Stops walking and cleaning files after `timeout`, building a partial prompt.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the configuration the prompts are built with.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the project directory.
+    pub fn project_dir(&self) -> &Path {
+        &self.project_dir
+    }
+
+    /// Returns a collector listing the files the prompts include.
+    pub fn files(&self) -> FileCollector<'_> {
+        let collector = FileCollector::new(&self.config, &self.project_dir).with_allow_outside_root(self.allow_outside_root);
+        match self.timeout {
+            Some(timeout) => collector.with_timeout(timeout),
+            None => collector,
+        }
+    }
+
+    /// Builds the prompt for `goal`, with the snippets of the configuration inserted and
+    /// `max_tokens` enforced like on the command line.
+    pub fn build(&self, goal: &str) -> io::Result<Prompt> {
+        let options = BuildOptions {
+            timeout: self.timeout,
+            allow_outside_root: self.allow_outside_root,
+            ..Default::default()
+        };
+        let mut warnings = Vec::new();
+        let generated = Generation::new(self.config.clone(), &self.project_dir, options).run_with_goal(goal, &mut warnings)?;
+        let mut text = Vec::new();
+        write_prompt(&mut text, &generated.context, &generated.rendered_goal, self.timeout)?;
+        let text = String::from_utf8_lossy(&text).into_owned();
+        Ok(Prompt {
+            tokens: self.config.tokenizer.count(&text),
+            text,
+            files: generated.context.files,
+            partial: generated.context.timed_out,
+            warnings: String::from_utf8_lossy(&warnings).lines().map(str::to_string).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_prompt_builder() {
+        let project_dir = TempDir::new("builder");
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::write(project_dir.join("src/main.rs"), "// Entry point\nfn main() {}\n").unwrap();
+        fs::write(project_dir.join("notes.md"), "# Notes\n```sh\nls\n```\n").unwrap();
+        let config = Config {
+            intro_prompt: "Intro".to_string(),
+            allowed_extensions: vec!["rs".to_string()],
+            ..Default::default()
+        };
+
+        let builder = PromptBuilder::new(config.clone(), &project_dir);
+        let paths: Vec<String> = builder.files().collect().into_iter().map(|file| file.path).collect();
+        assert_eq!(paths, vec!["src/main.rs"]);
+        let prompt = builder.build("Add a flag").unwrap();
+        assert!(prompt.text.starts_with("Intro\nFile: src/main.rs\n```\nfn main() {}\n```\n"));
+        assert!(prompt.text.ends_with("Specific Goal: Add a flag\n"));
+        assert_eq!(prompt.files.len(), 1);
+        assert!(!prompt.partial);
+
+        let hashed = Config { file_hashes: true, ..config.clone() };
+        let prompt = PromptBuilder::new(hashed.clone(), &project_dir).build("Add a flag").unwrap();
+        assert!(prompt.text.starts_with("Intro\nFile: src/main.rs (crc32 83899db0)\n"));
+
+        let templated = Config {
+            file_header_template: Some("<file path=\"{path}\" lang=\"{language}\" crc32=\"{crc32}\">".to_string()),
+            file_footer_template: Some("</file>".to_string()),
+            ..config.clone()
+        };
+        let prompt = PromptBuilder::new(templated, &project_dir).build("Add a flag").unwrap();
+        assert!(prompt.text.starts_with("Intro\n<file path=\"src/main.rs\" lang=\"rust\" crc32=\"83899db0\">\nfn main() {}\n</file>\n"));
+
+        let deterministic = Config {
+            deterministic: true,
+            project_name: "app".to_string(),
+            ..config.clone()
+        };
+        let prompt = PromptBuilder::new(deterministic.clone(), &project_dir).build("Add a flag").unwrap();
+        assert!(prompt.text.contains("```\n.\n└── src\n"));
+        assert_eq!(crate::prompt_filename(&deterministic), "app.txt");
+
+        let limited = Config {
+            max_file_bytes: Some(10),
+            ..hashed.clone()
+        };
+        let prompt = PromptBuilder::new(limited, &project_dir).build("Add a flag").unwrap();
+        assert!(prompt.text.starts_with("Intro\nFile: src/main.rs\n```\n[skipped: 28 bytes]\n```\n"));
+        assert_eq!(crate::format_size(2_345_678), "2.3 MB");
+
+        let config = Config {
+            allowed_extensions: vec!["rs".to_string(), "md".to_string()],
+            output_format: crate::config::OutputFormat::Markdown,
+            ..config
+        };
+        let prompt = PromptBuilder::new(config.clone(), &project_dir).build("Add a flag").unwrap();
+        assert!(prompt.text.starts_with("Intro\n### notes.md\n````markdown\n# Notes\n```sh\nls\n```\n````\n### src/main.rs\n```rust\n"));
+
+        // Scripts without an extension are handled as the language of their shebang line
+        fs::create_dir_all(project_dir.join("bin")).unwrap();
+        fs::write(project_dir.join("bin/deploy"), "#!/usr/bin/env python3\n# Deploy\nprint('ok')\n").unwrap();
+        let scripts = Config {
+            allowed_extensions: vec!["py".to_string()],
+            ..config.clone()
+        };
+        let prompt = PromptBuilder::new(scripts, &project_dir).build("Add a flag").unwrap();
+        assert!(prompt.text.starts_with("Intro\n### bin/deploy\n```python\nprint('ok')\n```\n"));
+        fs::remove_dir_all(project_dir.join("bin")).unwrap();
+
+        let config = Config {
+            output_format: crate::config::OutputFormat::Xml,
+            allowed_extensions: vec!["rs".to_string()],
+            answer_with_diff: true,
+            ..config
+        };
+        let prompt = PromptBuilder::new(config.clone(), &project_dir).build("Add a flag").unwrap();
+        assert!(prompt
+            .text
+            .starts_with("Intro\n<document>\n<source>src/main.rs</source>\n<document_contents>\nfn main() {}\n</document_contents>\n</document>\n"));
+        assert!(prompt.text.ends_with(&format!("Specific Goal: Add a flag\n{}\n", crate::i18n::strings(None).diff_instruction)));
+
+        // The closing section comes last, after the built-in instructions
+        let config = Config {
+            outro_prompt: Some("Answer with unified diffs only.\n".to_string()),
+            ..config
+        };
+        let prompt = PromptBuilder::new(config, &project_dir).build("Add a flag").unwrap();
+        assert!(prompt.text.ends_with("\nAnswer with unified diffs only.\n"));
+    }
+}
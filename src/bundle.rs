@@ -0,0 +1,136 @@
+// src/bundle.rs
+
+use std::io::{self, Cursor, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use zip::write::SimpleFileOptions;
+use zip::{DateTime, ZipWriter};
+
+use crate::anonymize::Anonymizer;
+use crate::config::Config;
+use crate::{read_cleaned_source, Context};
+
+/// Name of the bundle entry holding the intro, the tree and the goal.
+const INDEX_NAME: &str = "INDEX.md";
+
+/// Writes the prompt as a zip archive: every included file, cleaned, at its display path,
+/// plus an `INDEX.md` with the introductory prompt, the tree, the goal and the list of files
+/// matching `reference_globs`.
+///
+/// The archive is assembled in memory so that it can also be written to pipes.
+///
+/// # Arguments
+/// * `bundle_file` - Where the archive is written.
+/// * `context` - The context built for the prompt, whose files are cleaned again one by one.
+/// * `goal` - The goal as entered, anonymized with the rest of the index.
+/// * `timeout` - The `--timeout` value, mentioned in the index when the prompt is partial.
+/// * `anonymizer` - Applied to file paths and contents when `--anonymize` is given.
+pub fn write_bundle<W: Write>(
+    bundle_file: &mut W,
+    config: &Config,
+    project_dir: &Path,
+    context: &Context,
+    goal: &str,
+    timeout: Option<Duration>,
+    anonymizer: Option<&Anonymizer>,
+) -> io::Result<()> {
+    let anonymize = |text: &str| match anonymizer {
+        Some(anonymizer) => anonymizer.apply(text).0,
+        None => text.to_string(),
+    };
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    // Entries carry the fixed 1980-01-01 date of the format, so that identical prompts give identical archives
+    let options = SimpleFileOptions::default().last_modified_time(DateTime::default());
+
+    let mut index = String::new();
+    if let (true, Some(timeout)) = (context.timed_out, timeout) {
+        index.push_str(&format!("{}\n\n", context.strings.partial_notice(timeout)));
+    }
+    let strings = context.strings;
+    index.push_str(&format!(
+        "{}\n\n## {}\n\n```\n{}```\n\n## {}\n\n{}\n",
+        config.intro_prompt, strings.files, context.trees, strings.specific_goal, goal
+    ));
+    let references: Vec<&str> = context
+        .files
+        .iter()
+        .filter(|file| file.source.strip_prefix(project_dir).is_ok_and(|path| config.is_reference(path)))
+        .map(|file| file.path.as_str())
+        .collect();
+    if !references.is_empty() {
+        index.push_str(&format!("\n## {}\n\n", strings.reference_only));
+        for path in references {
+            index.push_str(&format!("- {}\n", path));
+        }
+    }
+    if context.answer_with_diff {
+        index.push_str(&format!("\n{}\n", strings.diff_instruction));
+    }
+    if context.answer_in_language {
+        index.push_str(&format!("\n{}\n", strings.answer_instruction));
+    }
+    if let Some(outro_prompt) = &context.outro_prompt {
+        index.push_str(&format!("\n{}\n", outro_prompt.trim_end()));
+    }
+    zip.start_file(INDEX_NAME, options).map_err(io::Error::other)?;
+    zip.write_all(anonymize(&index).as_bytes())?;
+
+    for file in &context.files {
+        let cleaned_content = read_cleaned_source(config, project_dir, &file.source)?;
+        zip.start_file(anonymize(&file.path), options).map_err(io::Error::other)?;
+        zip.write_all(anonymize(&cleaned_content).as_bytes())?;
+    }
+
+    let archive = zip.finish().map_err(io::Error::other)?;
+    bundle_file.write_all(archive.get_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Read;
+
+    use super::*;
+    use crate::testing::TempDir;
+    use crate::manifest::IncludedFile;
+
+    #[test]
+    fn test_write_bundle() {
+        let project_dir = TempDir::new("bundle");
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::write(project_dir.join("src/main.rs"), "// Entry point\nfn main() {}\n").unwrap();
+        let config = Config {
+            intro_prompt: "Intro".to_string(),
+            ..Default::default()
+        };
+        let context = Context {
+            content: Vec::new(),
+            files: vec![IncludedFile {
+                path: "src/main.rs".to_string(),
+                source: project_dir.join("src/main.rs"),
+            }],
+            trees: "└── src\n    └── main.rs\n".to_string(),
+            strings: config.strings(),
+            answer_in_language: false,
+            answer_with_diff: false,
+            outro_prompt: None,
+            timed_out: false,
+            exclusions: None,
+            images: Vec::new(),
+            redactions: Vec::new(),
+            warnings: Vec::new(),
+        };
+
+        let mut bundle = Vec::new();
+        write_bundle(&mut bundle, &config, &project_dir, &context, "Add a flag", None, None).unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bundle)).unwrap();
+        let mut read = |name: &str| {
+            let mut content = String::new();
+            archive.by_name(name).unwrap().read_to_string(&mut content).unwrap();
+            content
+        };
+        assert_eq!(read(INDEX_NAME), "Intro\n\n## Files\n\n```\n└── src\n    └── main.rs\n```\n\n## Specific Goal\n\nAdd a flag\n");
+        assert_eq!(read("src/main.rs"), "fn main() {}");
+    }
+}
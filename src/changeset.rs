@@ -0,0 +1,169 @@
+// src/changeset.rs
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use serde::Deserialize;
+
+use crate::manifest::IncludedFile;
+
+/// A changeset file: either a plain array of paths, or an object with a `files` array whose
+/// entries are paths or objects with a `path` field, as produced by many diff and deployment tools.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ChangesetFile {
+    Paths(Vec<Entry>),
+    Object { files: Vec<Entry> },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Path(PathBuf),
+    Object { path: PathBuf },
+}
+
+/// Reads the paths listed in a changeset file, relative to the project directory.
+pub fn load_changeset(path: &Path) -> Result<HashSet<PathBuf>, Box<dyn std::error::Error>> {
+    parse_changeset(&fs::read_to_string(path)?)
+}
+
+fn parse_changeset(content: &str) -> Result<HashSet<PathBuf>, Box<dyn std::error::Error>> {
+    let entries = match serde_json::from_str(content)? {
+        ChangesetFile::Paths(entries) | ChangesetFile::Object { files: entries } => entries,
+    };
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            Entry::Path(path) | Entry::Object { path } => path,
+        })
+        // `./src/main.rs` and `src/main.rs` name the same file
+        .map(|path| path.components().filter(|component| *component != Component::CurDir).collect())
+        .collect())
+}
+
+/// Lists the files added, copied, modified or renamed since `git_ref`, committed or not, plus
+/// the untracked files that are not ignored, relative to the project directory. Changes are
+/// counted from the merge base of `git_ref` and `HEAD`, so `origin/main` gives the changes of
+/// the current branch only.
+pub fn changed_since(project_dir: &Path, git_ref: &str) -> Result<HashSet<PathBuf>, Box<dyn std::error::Error>> {
+    let merge_base = git(project_dir, &["merge-base", git_ref, "HEAD"])?;
+    let mut changed = git(project_dir, &["diff", "-z", "--name-only", "--relative", "--diff-filter=ACMR", merge_base.trim()])?;
+    changed.push_str(&git(project_dir, &["ls-files", "-z", "--others", "--exclude-standard"])?);
+    Ok(changed.split('\0').filter(|path| !path.is_empty()).map(PathBuf::from).collect())
+}
+
+/// Lists the files added, copied, modified or renamed between the revisions `from` and `to`,
+/// relative to the project directory.
+pub fn changed_between(project_dir: &Path, from: &str, to: &str) -> Result<HashSet<PathBuf>, Box<dyn std::error::Error>> {
+    let changed = git(project_dir, &["diff", "-z", "--name-only", "--relative", "--diff-filter=ACMR", from, to, "--"])?;
+    Ok(changed.split('\0').filter(|path| !path.is_empty()).map(PathBuf::from).collect())
+}
+
+/// Which changes are added to the prompt as a diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitDiff {
+    /// The changes since a git ref, for `--git-diff`.
+    Since(String),
+    /// The changes staged for commit, for `--staged`.
+    Staged,
+    /// The changes between two revisions, for `--compare`.
+    Between(String, String),
+}
+
+impl GitDiff {
+    /// Returns the diff, with paths relative to the project directory.
+    pub fn diff(&self, project_dir: &Path) -> Result<String, Box<dyn std::error::Error>> {
+        match self {
+            GitDiff::Since(git_ref) => diff_since(project_dir, git_ref),
+            GitDiff::Staged => git(project_dir, &["diff", "--cached", "--no-color", "--no-ext-diff", "--relative"]),
+            GitDiff::Between(from, to) => git(project_dir, &["diff", "--no-color", "--no-ext-diff", "--relative", from, to, "--"]),
+        }
+    }
+}
+
+/// Returns the files of the project directory staged for commit (added, modified or renamed),
+/// relative to the project directory.
+pub fn staged(project_dir: &Path) -> Result<HashSet<PathBuf>, Box<dyn std::error::Error>> {
+    let changed = git(project_dir, &["diff", "--cached", "-z", "--name-only", "--relative", "--diff-filter=ACMR"])?;
+    Ok(changed.split('\0').filter(|path| !path.is_empty()).map(PathBuf::from).collect())
+}
+
+/// Returns the `git diff` of the project directory since `git_ref`, counted from the merge base
+/// like [`changed_since`], with paths relative to the project directory.
+pub fn diff_since(project_dir: &Path, git_ref: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let merge_base = git(project_dir, &["merge-base", git_ref, "HEAD"])?;
+    git(project_dir, &["diff", "--no-color", "--no-ext-diff", "--relative", merge_base.trim()])
+}
+
+/// Runs git in `dir` and returns its output, or its error message when it fails.
+fn git(dir: &Path, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Returns the changeset paths that are not among the `included` files, sorted.
+pub fn missing_files<'a>(changeset: &'a HashSet<PathBuf>, included: &[IncludedFile], project_dir: &Path) -> Vec<&'a PathBuf> {
+    let included: HashSet<&Path> = included
+        .iter()
+        .filter_map(|file| file.source.strip_prefix(project_dir).ok())
+        .collect();
+    let mut missing: Vec<&PathBuf> = changeset.iter().filter(|path| !included.contains(path.as_path())).collect();
+    missing.sort();
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_parse_changeset() {
+        let expected: HashSet<PathBuf> = [PathBuf::from("src/main.rs"), PathBuf::from("Cargo.toml")].into();
+        assert_eq!(parse_changeset(r#"["./src/main.rs", "Cargo.toml"]"#).unwrap(), expected);
+        assert_eq!(parse_changeset(r#"{"files": [{"path": "src/main.rs", "status": "modified"}, "Cargo.toml"]}"#).unwrap(), expected);
+        assert!(parse_changeset(r#"{"paths": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_changed_since() {
+        let dir = TempDir::new("changed-since");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        let git = |args: &[&str]| assert!(Command::new("git").arg("-C").arg(&*dir).args(args).output().unwrap().status.success());
+        git(&["init", "-q"]);
+        fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("src/lib.rs"), "").unwrap();
+        git(&["add", "."]);
+        git(&["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "-q", "-m", "initial"]);
+        fs::write(dir.join("src/main.rs"), "fn main() { run() }\n").unwrap();
+        fs::write(dir.join("src/new.rs"), "").unwrap();
+
+        let expected: HashSet<PathBuf> = [PathBuf::from("src/main.rs"), PathBuf::from("src/new.rs")].into();
+        assert_eq!(changed_since(&dir, "HEAD").unwrap(), expected);
+        let expected: HashSet<PathBuf> = [PathBuf::from("main.rs"), PathBuf::from("new.rs")].into();
+        assert_eq!(changed_since(&dir.join("src"), "HEAD").unwrap(), expected);
+        let diff = diff_since(&dir.join("src"), "HEAD").unwrap();
+        assert!(diff.starts_with("diff --git a/main.rs b/main.rs\n"));
+        assert!(diff.contains("-fn main() {}\n+fn main() { run() }\n"));
+        assert!(changed_since(&dir, "no-such-ref").is_err());
+
+        assert!(staged(&dir).unwrap().is_empty());
+        git(&["add", "src/new.rs"]);
+        assert_eq!(staged(&dir).unwrap(), [PathBuf::from("src/new.rs")].into());
+        let diff = GitDiff::Staged.diff(&dir).unwrap();
+        assert!(diff.starts_with("diff --git a/src/new.rs b/src/new.rs\nnew file mode"));
+        assert!(!diff.contains("main.rs"));
+
+        git(&["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "-q", "-m", "second"]);
+        assert_eq!(changed_between(&dir, "HEAD~1", "HEAD").unwrap(), [PathBuf::from("src/new.rs")].into());
+        let diff = GitDiff::Between("HEAD~1".to_string(), "HEAD".to_string()).diff(&dir).unwrap();
+        assert!(diff.starts_with("diff --git a/src/new.rs b/src/new.rs\nnew file mode"));
+        assert!(changed_between(&dir, "HEAD", "no-such-ref").is_err());
+    }
+}
@@ -0,0 +1,693 @@
+// src/clean.rs
+
+use no_comment::{IntoWithoutComments as _, languages};
+use unicode_width::{UnicodeWidthChar as _, UnicodeWidthStr as _};
+
+use crate::config::{CommentEngine, EmptyLines};
+
+/// Marks the start of an original line number embedded in the content while comments are removed.
+const LINE_MARK: char = '\u{E000}';
+/// Marks the end of an embedded original line number.
+const LINE_MARK_END: char = '\u{E001}';
+/// Character starting the placeholder of a doc comment kept by `remove_non_doc_comments`.
+const DOC_MARK: char = '\u{E002}';
+/// Character ending the placeholder of a doc comment.
+const DOC_MARK_END: char = '\u{E003}';
+
+/// Minimum length of a run of base64 or hex characters to be considered an embedded blob.
+const BLOB_MIN_LENGTH: usize = 100;
+/// Minimum Shannon entropy of such a run, in bits per character. Random hex data has about
+/// 4, base64 data nearly 6, while repetitive runs (`====`, `AAAA...`) stay well below.
+const BLOB_MIN_ENTROPY: f64 = 3.5;
+/// Minimum share of digits in such a run, which sets encoded data apart from long paths and
+/// identifiers made of words.
+const BLOB_MIN_DIGITS: f64 = 0.05;
+
+/// Marker opening a region of a file to include, e.g. `// prompt-gen:begin`.
+const REGION_BEGIN: &str = "prompt-gen:begin";
+/// Marker closing a region opened by [`REGION_BEGIN`].
+const REGION_END: &str = "prompt-gen:end";
+
+/// How the content of a file is cleaned before being included in the prompt.
+pub struct CleanOptions {
+    /// Whether comments are removed.
+    pub strip_comments: bool,
+    /// Whether doc comments are kept when comments are removed.
+    pub keep_doc_comments: bool,
+    /// How comments are found.
+    pub engine: CommentEngine,
+    /// How blank lines are handled.
+    pub empty_lines: EmptyLines,
+    /// Whether each block of retained lines is preceded by an `[orig lines a-b]` marker.
+    pub annotate_spans: bool,
+}
+
+/// Cleans the file content according to `options`.
+///
+/// # Arguments
+/// * `file_content` - The content of the file as a string.
+/// * `extension` - The file extension indicating the programming language (e.g., "rs", "c", "py").
+/// * `options` - Which cleaning steps to apply.
+///
+/// # Returns
+/// The cleaned content, without a trailing newline.
+pub fn clean_content(file_content: &str, extension: &str, options: &CleanOptions) -> String {
+    if !options.annotate_spans {
+        let without_comments = if options.strip_comments {
+            remove_comments_parsed(file_content, extension, options, false)
+                .unwrap_or_else(|| remove_non_doc_comments(file_content, extension, options.keep_doc_comments))
+        } else {
+            file_content.to_string()
+        };
+        return handle_empty_lines(&without_comments, options.empty_lines);
+    }
+
+    let numbered = if options.strip_comments {
+        remove_comments_numbered(file_content, extension, options)
+    } else {
+        file_content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.to_string()))
+            .collect()
+    };
+    annotate_spans(&numbered, file_content.lines().count(), options.empty_lines)
+}
+
+/// Removes comments from the file content based on the file extension.
+///
+/// # Arguments
+/// * `file_content` - The content of the file as a string.
+/// * `extension` - The file extension indicating the programming language (e.g., "rs", "c", "py").
+///
+/// # Returns
+/// A new string with comments removed, according to the syntax of the specified programming language.
+pub fn remove_comments(file_content: &str, extension: &str) -> String {
+    match extension {
+        "rs" => file_content
+            .chars()
+            .without_comments(languages::rust())
+            .collect::<String>(),
+        "c" => file_content
+            .chars()
+            .without_comments(languages::c())
+            .collect::<String>(),
+        "py" => file_content
+            .chars()
+            .without_comments(languages::python())
+            .collect::<String>(),
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "go" | "java" | "cpp" | "cc" | "cxx" | "h" | "hpp" | "hh" => strip_comments(file_content, &C_LIKE),
+        "sh" | "bash" | "zsh" => strip_comments(file_content, &SHELL),
+        "rb" => strip_comments(file_content, &RUBY),
+        "sql" => strip_comments(file_content, &SQL),
+        _ => file_content.to_string(), // If the extension is not recognized, return the original content.
+    }
+}
+
+/// Removes comments like [`remove_comments`], but keeps doc comments (`///`, `//!`, `/** */`,
+/// `/*! */` and Python docstrings) when `keep_doc_comments` is set.
+///
+/// Doc comments are swapped for placeholders between `DOC_MARK` and `DOC_MARK_END` while the
+/// other comments are removed, then put back.
+fn remove_non_doc_comments(file_content: &str, extension: &str, keep_doc_comments: bool) -> String {
+    if !keep_doc_comments {
+        return remove_comments(file_content, extension);
+    }
+    let (masked, docs) = mask_doc_comments(file_content, extension);
+    if docs.is_empty() {
+        return remove_comments(file_content, extension);
+    }
+    let stripped = remove_comments(&masked, extension);
+    let mut result = String::with_capacity(file_content.len());
+    let mut rest = stripped.as_str();
+    while let Some(start) = rest.find(DOC_MARK) {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + DOC_MARK.len_utf8()..];
+        match after.split_once(DOC_MARK_END).and_then(|(index, tail)| Some((docs.get(index.parse::<usize>().ok()?)?, tail))) {
+            Some((doc, tail)) => {
+                result.push_str(doc);
+                rest = tail;
+            }
+            None => {
+                result.push(DOC_MARK);
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Replaces the doc comments of `file_content` with numbered placeholders, and returns the
+/// masked content with the doc comments in order. Only doc comments starting their line, after
+/// indentation, are recognized, which leaves out markers inside strings and other comments.
+fn mask_doc_comments(file_content: &str, extension: &str) -> (String, Vec<String>) {
+    let (line_markers, blocks): (&[&str], &[(&str, &str)]) = match extension {
+        "rs" | "c" | "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "go" | "java" | "cpp" | "cc" | "cxx" | "h" | "hpp" | "hh" => {
+            (&["///", "//!"], &[("/**", "*/"), ("/*!", "*/")])
+        }
+        "py" => (&[], &[("\"\"\"", "\"\"\""), ("'''", "'''")]),
+        _ => return (file_content.to_string(), Vec::new()),
+    };
+
+    let mut masked = String::with_capacity(file_content.len());
+    let mut docs = Vec::new();
+    let mut i = 0;
+    let mut line_start = 0;
+    while let Some(c) = file_content[i..].chars().next() {
+        let rest = &file_content[i..];
+        let indent = &file_content[line_start..i];
+        // Lines numbered by `remove_comments_numbered` start with their mark
+        let indent = indent.rsplit(LINE_MARK_END).next().unwrap_or(indent);
+        let doc_len = if indent.trim().is_empty() {
+            doc_comment_len(rest, line_markers, blocks)
+        } else {
+            None
+        };
+        match doc_len {
+            Some(len) => {
+                masked.push_str(&format!("{}{}{}", DOC_MARK, docs.len(), DOC_MARK_END));
+                docs.push(rest[..len].to_string());
+                i += len;
+            }
+            None => {
+                masked.push(c);
+                i += c.len_utf8();
+                if c == '\n' {
+                    line_start = i;
+                }
+            }
+        }
+    }
+    (masked, docs)
+}
+
+/// Returns the length of the doc comment starting `rest`, if it starts with one. A line doc
+/// comment runs to the end of its line; `////` and `/***` banners are not doc comments.
+fn doc_comment_len(rest: &str, line_markers: &[&str], blocks: &[(&str, &str)]) -> Option<usize> {
+    let is_banner = |marker: &str| rest[marker.len()..].starts_with(['/', '*']);
+    if let Some(marker) = line_markers.iter().find(|marker| rest.starts_with(*marker)) {
+        return (!is_banner(marker)).then(|| rest.find('\n').unwrap_or(rest.len()));
+    }
+    let (open, close) = blocks.iter().find(|(open, _)| rest.starts_with(open))?;
+    if open.starts_with('/') && is_banner(open) {
+        return None;
+    }
+    Some(rest[open.len()..].find(close).map_or(rest.len(), |end| open.len() + end + close.len()))
+}
+
+/// Comment syntax of the languages stripped by `strip_comments`, which unlike `no_comment`
+/// leaves string literals alone, so that URLs in JavaScript or `#{}` in Ruby strings survive.
+struct CommentSyntax {
+    /// Markers starting a comment that runs to the end of the line.
+    line: &'static [&'static str],
+    /// Markers opening and closing a block comment.
+    block: Option<(&'static str, &'static str)>,
+    /// Quotes delimiting string literals.
+    quotes: &'static [char],
+    /// Whether line comments only start a word, as `#` in shell scripts (`$#` is not one).
+    word_start: bool,
+}
+
+/// JavaScript, TypeScript, Go, Java and C++.
+const C_LIKE: CommentSyntax = CommentSyntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+    word_start: false,
+};
+
+const SHELL: CommentSyntax = CommentSyntax {
+    line: &["#"],
+    block: None,
+    quotes: &['"', '\''],
+    word_start: true,
+};
+
+const RUBY: CommentSyntax = CommentSyntax {
+    line: &["#"],
+    block: None,
+    quotes: &['"', '\''],
+    word_start: false,
+};
+
+const SQL: CommentSyntax = CommentSyntax {
+    line: &["--"],
+    block: Some(("/*", "*/")),
+    quotes: &['\'', '"'],
+    word_start: false,
+};
+
+/// Removes the comments of `file_content` according to `syntax`, outside string literals.
+/// Line comments leave their newline, block comments are removed with the lines they span.
+fn strip_comments(file_content: &str, syntax: &CommentSyntax) -> String {
+    let mut result = String::with_capacity(file_content.len());
+    let mut i = 0;
+    while let Some(c) = file_content[i..].chars().next() {
+        let rest = &file_content[i..];
+        if syntax.quotes.contains(&c) {
+            let end = string_end(rest, c);
+            result.push_str(&rest[..end]);
+            i += end;
+        } else if let Some((open, close)) = syntax.block.filter(|(open, _)| rest.starts_with(open)) {
+            i += rest[open.len()..].find(close).map_or(rest.len(), |end| open.len() + end + close.len());
+        } else if syntax.line.iter().any(|marker| rest.starts_with(marker))
+            && (!syntax.word_start || file_content[..i].chars().next_back().is_none_or(|previous| previous.is_whitespace() || matches!(previous, ';' | LINE_MARK_END)))
+        {
+            i += rest.find('\n').unwrap_or(rest.len());
+        } else {
+            result.push(c);
+            i += c.len_utf8();
+        }
+    }
+    result
+}
+
+/// Returns the length of the string literal starting `rest` with `quote`, up to its closing
+/// quote or to the end of the content when it is not closed. Backslashes escape the next character.
+fn string_end(rest: &str, quote: char) -> usize {
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == quote {
+            return i + c.len_utf8();
+        }
+    }
+    rest.len()
+}
+
+/// Removes comments while keeping track of the original line each remaining line starts on.
+///
+/// Every line is prefixed with its number between `LINE_MARK` and `LINE_MARK_END` before the
+/// comments are removed. Lines swallowed by a multi-line comment lose their mark along with
+/// their content, so each remaining line still starts with the number of its original line.
+fn remove_comments_numbered(file_content: &str, extension: &str, options: &CleanOptions) -> Vec<(usize, String)> {
+    let stripped = remove_comments_parsed(file_content, extension, options, true).unwrap_or_else(|| {
+        let marked: String = file_content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| format!("{}{}{}{}\n", LINE_MARK, i + 1, LINE_MARK_END, line))
+            .collect();
+        remove_non_doc_comments(&marked, extension, options.keep_doc_comments)
+    });
+
+    let mut number = 1;
+    stripped
+        .lines()
+        .map(|line| {
+            let text = match line.strip_prefix(LINE_MARK).and_then(|rest| rest.split_once(LINE_MARK_END)) {
+                Some((mark, text)) => {
+                    number = mark.parse().unwrap_or(number);
+                    text
+                }
+                None => line,
+            };
+            (number, text.to_string())
+        })
+        .collect()
+}
+
+/// Removes the comments found by the tree-sitter grammar of `extension` when `options` select
+/// that engine, keeping the doc comments with `keep_doc_comments`. With `number_lines`, the lines
+/// start with their number like in `remove_comments_numbered`, except those starting inside a
+/// removed comment. Returns `None` when the stream engine applies instead, including for
+/// languages without a grammar.
+#[cfg(feature = "tree-sitter")]
+fn remove_comments_parsed(file_content: &str, extension: &str, options: &CleanOptions, number_lines: bool) -> Option<String> {
+    if options.engine != CommentEngine::TreeSitter {
+        return None;
+    }
+    let comments = crate::treesitter::comments(file_content, extension)?;
+    let mut removed = comments.into_iter().filter(|comment| !(options.keep_doc_comments && comment.is_doc)).map(|comment| comment.range).peekable();
+    let mut result = String::with_capacity(file_content.len());
+    let mut line = 0;
+    let mut line_start = true;
+    for (i, c) in file_content.char_indices() {
+        while removed.next_if(|range| range.end <= i).is_some() {}
+        let in_comment = removed.peek().is_some_and(|range| range.contains(&i));
+        if line_start {
+            line += 1;
+            // A line keeps its number unless a comment opened on an earlier line swallows it
+            if number_lines && !removed.peek().is_some_and(|range| range.start < i && i < range.end) {
+                result.push_str(&format!("{}{}{}", LINE_MARK, line, LINE_MARK_END));
+            }
+        }
+        if !in_comment {
+            result.push(c);
+        }
+        line_start = c == '\n';
+    }
+    Some(result)
+}
+
+/// Without the `tree-sitter` feature, the stream engine always applies, with a warning when the
+/// tree-sitter engine is selected.
+#[cfg(not(feature = "tree-sitter"))]
+fn remove_comments_parsed(_file_content: &str, _extension: &str, options: &CleanOptions, _number_lines: bool) -> Option<String> {
+    if options.engine == CommentEngine::TreeSitter {
+        static WARNING: std::sync::Once = std::sync::Once::new();
+        WARNING.call_once(|| eprintln!("Warning: built without the tree-sitter feature, comments are stripped with the stream engine instead."));
+    }
+    None
+}
+
+/// Applies the blank-line handling to numbered lines and precedes each block of consecutive
+/// retained lines with the range of original lines it covers.
+fn annotate_spans(numbered: &[(usize, String)], total_lines: usize, mode: EmptyLines) -> String {
+    let lines: Vec<&str> = numbered.iter().map(|(_, text)| text.as_str()).collect();
+    let kept = kept_lines(&lines, mode);
+
+    let mut output = Vec::new();
+    let mut block_start = 0;
+    for (k, &i) in kept.iter().enumerate() {
+        // A block ends before a dropped line, since line offsets no longer match past it
+        if kept.get(k + 1) == Some(&(i + 1)) {
+            continue;
+        }
+        let first = numbered[kept[block_start]].0;
+        // The last line also covers the lines merged into it by a multi-line comment
+        let last = numbered.get(i + 1).map_or(total_lines, |(number, _)| number - 1);
+        output.push(if first == last {
+            format!("[orig line {}]", first)
+        } else {
+            format!("[orig lines {}-{}]", first, last)
+        });
+        output.extend(kept[block_start..=k].iter().map(|&j| render_line(lines[j], mode).to_string()));
+        block_start = k + 1;
+    }
+    output.join("\n")
+}
+
+/// Marker ending a line that continues on the next one after soft-wrapping.
+const WRAP_MARKER: char = '↩';
+
+/// Soft-wraps the lines wider than `columns` columns: each piece but the last is cut one column
+/// short and ends with `↩`, so that the original line can be told apart. Wide characters, such
+/// as CJK ideographs, take two columns and are never split across pieces.
+pub fn wrap_lines(content: &str, columns: usize) -> String {
+    if columns < 2 {
+        return content.to_string();
+    }
+    let mut wrapped = Vec::new();
+    for line in content.split('\n') {
+        let mut piece = String::new();
+        let mut width = 0;
+        let mut remaining = line.width();
+        for c in line.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if width + char_width > columns - 1 && width + remaining > columns {
+                piece.push(WRAP_MARKER);
+                wrapped.push(std::mem::take(&mut piece));
+                width = 0;
+            }
+            piece.push(c);
+            width += char_width;
+            remaining = remaining.saturating_sub(char_width);
+        }
+        wrapped.push(piece);
+    }
+    wrapped.join("\n")
+}
+
+/// Keeps only the regions of a file delimited by `prompt-gen:begin` and `prompt-gen:end`
+/// lines, alone in a comment of any syntax, and replaces each run of lines left out with a
+/// `[... N lines omitted ...]` note. A region left open runs to the end of the file.
+///
+/// # Returns
+/// The selected regions, or `None` when the file has no `prompt-gen:begin` marker.
+pub fn select_regions(content: &str) -> Option<String> {
+    if !has_regions(content) {
+        return None;
+    }
+    let mut selected = Vec::new();
+    let mut in_region = false;
+    let mut omitted = 0;
+    for line in content.lines() {
+        if is_marker(line, REGION_BEGIN) {
+            in_region = true;
+        } else if is_marker(line, REGION_END) {
+            in_region = false;
+        } else if in_region {
+            if omitted > 0 {
+                selected.push(omitted_note(omitted));
+                omitted = 0;
+            }
+            selected.push(line.to_string());
+        } else {
+            omitted += 1;
+        }
+    }
+    if omitted > 0 {
+        selected.push(omitted_note(omitted));
+    }
+    Some(selected.join("\n"))
+}
+
+/// Returns whether the content has a `prompt-gen:begin` marker line.
+pub fn has_regions(content: &str) -> bool {
+    content.contains(REGION_BEGIN) && content.lines().any(|line| is_marker(line, REGION_BEGIN))
+}
+
+/// Returns whether `line` holds nothing but `marker` and comment delimiters, such as
+/// `// prompt-gen:begin` or `<!-- prompt-gen:end -->`, so that code mentioning a marker is
+/// not taken for one.
+fn is_marker(line: &str, marker: &str) -> bool {
+    let is_delimiter = |c: char| c.is_whitespace() || matches!(c, '/' | '*' | '#' | '-' | ';' | '<' | '>' | '!');
+    line.trim_matches(is_delimiter) == marker
+}
+
+/// Returns the note replacing `lines` lines left out of the prompt, e.g. `[... 12 lines omitted ...]`.
+pub fn omitted_note(lines: usize) -> String {
+    if lines == 1 {
+        "[... 1 line omitted ...]".to_string()
+    } else {
+        format!("[... {} lines omitted ...]", lines)
+    }
+}
+
+/// Replaces long high-entropy runs of base64 or hex characters, such as embedded assets or
+/// inline minified data, with `[binary data omitted: N bytes]` markers.
+///
+/// # Returns
+/// The content with blobs replaced, and the number of blobs found.
+pub fn omit_blobs(content: &str) -> (String, usize) {
+    let is_blob_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=' | '-' | '_');
+    let mut result = String::with_capacity(content.len());
+    let mut count = 0;
+    let mut rest = content;
+    while let Some(start) = rest.find(is_blob_char) {
+        let end = rest[start..].find(|c: char| !is_blob_char(c)).map_or(rest.len(), |len| start + len);
+        let run = &rest[start..end];
+        result.push_str(&rest[..start]);
+        if is_blob(run) {
+            result.push_str(&format!("[binary data omitted: {} bytes]", run.len()));
+            count += 1;
+        } else {
+            result.push_str(run);
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    (result, count)
+}
+
+fn is_blob(run: &str) -> bool {
+    let digits = run.bytes().filter(u8::is_ascii_digit).count();
+    run.len() >= BLOB_MIN_LENGTH && digits as f64 >= run.len() as f64 * BLOB_MIN_DIGITS && entropy(run) >= BLOB_MIN_ENTROPY
+}
+
+/// Shannon entropy of an ASCII string, in bits per character.
+pub(crate) fn entropy(run: &str) -> f64 {
+    let mut counts = [0usize; 128];
+    for byte in run.bytes() {
+        counts[usize::from(byte & 0x7f)] += 1;
+    }
+    let len = run.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Applies the configured blank-line handling to the file content. Whitespace-only lines,
+/// such as those left behind by removed comments, count as blank.
+pub fn handle_empty_lines(input: &str, mode: EmptyLines) -> String {
+    let lines: Vec<&str> = input.lines().collect();
+    kept_lines(&lines, mode)
+        .into_iter()
+        .map(|i| render_line(lines[i], mode))
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+/// Returns the indices of the lines kept by the blank-line handling. Trailing blank lines are always dropped.
+fn kept_lines(lines: &[&str], mode: EmptyLines) -> Vec<usize> {
+    let blank = |i: usize| lines[i].trim().is_empty();
+    let mut kept: Vec<usize> = Vec::new();
+    for i in 0..lines.len() {
+        let keep = match mode {
+            EmptyLines::Keep => true,
+            // Skip leading blank lines and blank lines following another blank line
+            EmptyLines::Collapse => !blank(i) || kept.last().is_some_and(|&last| !blank(last)),
+            EmptyLines::Strip => !blank(i),
+        };
+        if keep {
+            kept.push(i);
+        }
+    }
+    while kept.last().is_some_and(|&last| blank(last)) {
+        kept.pop();
+    }
+    kept
+}
+
+/// Blank lines kept by `collapse` are emitted empty, `keep` leaves them untouched.
+fn render_line(line: &str, mode: EmptyLines) -> &str {
+    if mode != EmptyLines::Keep && line.trim().is_empty() {
+        ""
+    } else {
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_lines() {
+        assert_eq!(wrap_lines("short\nabcdefghij\n", 4), "sho↩\nrt\nabc↩\ndef↩\nghij\n");
+        assert_eq!(wrap_lines("abcd", 4), "abcd");
+        assert_eq!(wrap_lines("abcd", 0), "abcd");
+        assert_eq!(wrap_lines("// 日本語のコメント", 8), "// 日本↩\n語のコ↩\nメント");
+    }
+
+    #[test]
+    fn test_omit_blobs() {
+        let base64 = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg+/Zx9Qw3Lm";
+        let content = format!("const LOGO: &str = \"{}\";\nlet sep = \"{}\";", base64, "=".repeat(120));
+        let (cleaned, count) = omit_blobs(&content);
+        assert_eq!(count, 1);
+        assert_eq!(cleaned, format!("const LOGO: &str = \"[binary data omitted: {} bytes]\";\nlet sep = \"{}\";", base64.len(), "=".repeat(120)));
+
+        let code = "fn main() { let very_long_name = compute(); }";
+        assert_eq!(omit_blobs(code), (code.to_string(), 0));
+        let path = "include_str!(\"../../assets/templates/email/account_verification/reminder_after_signup_template_body.html\")";
+        assert_eq!(omit_blobs(path), (path.to_string(), 0));
+    }
+
+    #[test]
+    fn test_select_regions() {
+        let content = "use std::io;\n\n// prompt-gen:begin\nfn parse() {}\n// prompt-gen:end\nfn a() {}\nfn b() {}\n# prompt-gen:begin\nfn tail() {}\n";
+        assert_eq!(
+            select_regions(content).unwrap(),
+            "[... 2 lines omitted ...]\nfn parse() {}\n[... 2 lines omitted ...]\nfn tail() {}"
+        );
+        assert_eq!(select_regions("// prompt-gen:begin\nfn main() {}\n// prompt-gen:end\nfn x() {}").unwrap(), "fn main() {}\n[... 1 line omitted ...]");
+        assert_eq!(select_regions("fn main() {}\n"), None);
+        let mention = "const BEGIN: &str = \"prompt-gen:begin\";\nfn main() {}";
+        assert_eq!(select_regions(mention), None);
+    }
+
+    #[test]
+    fn test_remove_comments() {
+        let js = "// Fetch\nconst url = \"https://example.com\"; /* the API\n */ get(url, '/*'); // done\n";
+        assert_eq!(remove_comments(js, "ts"), "\nconst url = \"https://example.com\";  get(url, '/*'); \n");
+        let go = "func main() { // entry\n\tfmt.Println(`a // b`, '\\'')\n}\n";
+        assert_eq!(remove_comments(go, "go"), "func main() { \n\tfmt.Println(`a // b`, '\\'')\n}\n");
+        let shell = "#!/bin/sh\n# Count\necho $# \"#1\" ${#files[@]} # args\n";
+        assert_eq!(remove_comments(shell, "sh"), "\n\necho $# \"#1\" ${#files[@]} \n");
+        let ruby = "puts \"#{name}\" # greet\n";
+        assert_eq!(remove_comments(ruby, "rb"), "puts \"#{name}\" \n");
+        let sql = "SELECT 'it''s -- here' -- why\nFROM t /* all */;\n";
+        assert_eq!(remove_comments(sql, "sql"), "SELECT 'it''s -- here' \nFROM t ;\n");
+    }
+
+    #[test]
+    fn test_keep_doc_comments() {
+        let options = CleanOptions {
+            strip_comments: true,
+            keep_doc_comments: true,
+            engine: CommentEngine::Stream,
+            empty_lines: EmptyLines::Strip,
+            annotate_spans: false,
+        };
+        let rust = "//! Crate docs\n//// banner\n/// Runs.\n// TODO\nfn run() {} // done\n/**\n * Block docs\n */\n/* note */\n";
+        assert_eq!(
+            clean_content(rust, "rs", &options),
+            "//! Crate docs\n/// Runs.\nfn run() {} \n/**\n * Block docs\n */"
+        );
+        let python = "def run():\n    \"\"\"Runs.\"\"\"\n    # step\n    pass\n";
+        assert_eq!(clean_content(python, "py", &options), "def run():\n    \"\"\"Runs.\"\"\"\n    pass");
+
+        let options = CleanOptions { annotate_spans: true, ..options };
+        assert_eq!(
+            clean_content("// a\n/// b\nfn f() {}\n", "rs", &options),
+            "[orig lines 2-3]\n/// b\nfn f() {}"
+        );
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    #[test]
+    fn test_tree_sitter_engine() {
+        let options = CleanOptions {
+            strip_comments: true,
+            keep_doc_comments: true,
+            engine: CommentEngine::TreeSitter,
+            empty_lines: EmptyLines::Strip,
+            annotate_spans: false,
+        };
+        // Comment markers inside raw strings and nested block comments
+        let rust = "/// Runs.\nfn run() {\n    let raw = r#\"/* \"not\" a comment */\"#; // done\n    /* outer /* nested */ still */ go();\n}\n";
+        assert_eq!(clean_content(rust, "rs", &options), "/// Runs.\nfn run() {\n    let raw = r#\"/* \"not\" a comment */\"#; \n     go();\n}");
+        let options = CleanOptions { keep_doc_comments: false, ..options };
+        assert_eq!(clean_content("def f():\n    \"\"\"Docs.\"\"\"\n    return '# not'\n", "py", &options), "def f():\n    return '# not'");
+        // Languages without a grammar go through the stream engine
+        assert_eq!(clean_content("SELECT 1 -- one\n", "sql", &options), "SELECT 1 ");
+
+        let options = CleanOptions { annotate_spans: true, ..options };
+        let input = "use std::io;\n\n/* block\n   comment */\nfn main() { /* inline\n */ run();\n    // line comment\n    done();\n}\n";
+        assert_eq!(
+            clean_content(input, "rs", &options),
+            "[orig line 1]\nuse std::io;\n[orig lines 5-6]\nfn main() {  run();\n[orig lines 8-9]\n    done();\n}"
+        );
+    }
+
+    #[test]
+    fn test_handle_empty_lines() {
+        let input = "\nimport os\n\n\n   \ndef main():\n    pass\n\n";
+        assert_eq!(handle_empty_lines(input, EmptyLines::Keep), "\nimport os\n\n\n   \ndef main():\n    pass");
+        assert_eq!(handle_empty_lines(input, EmptyLines::Collapse), "import os\n\ndef main():\n    pass");
+        assert_eq!(handle_empty_lines(input, EmptyLines::Strip), "import os\ndef main():\n    pass");
+    }
+
+    #[test]
+    fn test_annotate_spans() {
+        let input = "use std::io;\n\n/* block\n   comment */\nfn main() { /* inline\n */ run();\n    // line comment\n    done();\n}\n";
+        let options = CleanOptions {
+            strip_comments: true,
+            keep_doc_comments: false,
+            engine: CommentEngine::Stream,
+            empty_lines: EmptyLines::Strip,
+            annotate_spans: true,
+        };
+        assert_eq!(
+            clean_content(input, "rs", &options),
+            "[orig line 1]\nuse std::io;\n[orig lines 5-6]\nfn main() {  run();\n[orig lines 8-9]\n    done();\n}"
+        );
+
+        let options = CleanOptions {
+            strip_comments: false,
+            keep_doc_comments: false,
+            engine: CommentEngine::Stream,
+            empty_lines: EmptyLines::Keep,
+            annotate_spans: true,
+        };
+        assert_eq!(clean_content("a\n\nb\n", "txt", &options), "[orig lines 1-3]\na\n\nb");
+    }
+}
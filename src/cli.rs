@@ -0,0 +1,499 @@
+// src/cli.rs
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::config::OutputFormat;
+
+/// Generates structured LLM prompts from the files of the current project.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Work on the project in this directory instead of the current one: its configuration is
+    /// used and its files are walked, so prompt-gen can run from anywhere (cron jobs, editors).
+    #[arg(long, global = true, value_name = "PATH")]
+    pub project: Option<PathBuf>,
+
+    /// The specific goal of the prompt. Given a goal, prompt-gen runs without asking anything:
+    /// a missing project configuration is created from the detected defaults.
+    #[arg(long)]
+    pub goal: Option<String>,
+
+    /// Read the goal from this file, or from stdin with `-`, e.g. for long goals drafted in an
+    /// editor. Like `--goal`, prompt-gen then runs without asking anything.
+    #[arg(long, value_name = "PATH", conflicts_with = "goal")]
+    pub goal_file: Option<PathBuf>,
+
+    /// Use the goal template of this name, from `goal_templates`, as the goal. The value of each
+    /// `{name}` placeholder is asked for, unless given with `--var`.
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["goal", "goal_file"])]
+    pub template: Option<String>,
+
+    /// Fill the `{NAME}` placeholders of the `--template` goal with this value (repeatable).
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var, requires = "template")]
+    pub vars: Vec<(String, String)>,
+
+    /// Use this project name for this run instead of the configured one.
+    #[arg(long, value_name = "NAME")]
+    pub project_name: Option<String>,
+
+    /// Use this introductory prompt for this run instead of the configured one.
+    #[arg(long, value_name = "TEXT")]
+    pub intro_prompt: Option<String>,
+
+    /// Use this closing section, after the goal, for this run instead of the configured one.
+    #[arg(long, value_name = "TEXT")]
+    pub outro_prompt: Option<String>,
+
+    /// Include files with these extensions, or `@group`s, for this run instead of the
+    /// configured ones (comma-separated).
+    #[arg(long, value_name = "EXTENSIONS", value_delimiter = ',')]
+    pub allowed_extensions: Option<Vec<String>>,
+
+    /// Skip directories matching these patterns for this run instead of the configured ones
+    /// (comma-separated).
+    #[arg(long, value_name = "PATTERNS", value_delimiter = ',')]
+    pub deny_dirs: Option<Vec<String>>,
+
+    /// Write the prompt scaffolding in this language for this run (ISO 639-1 code).
+    #[arg(long)]
+    pub language: Option<String>,
+
+    /// End the prompt by asking the model to answer with a unified diff against the file paths
+    /// of the prompt, as with `answer_with_diff` in the configuration.
+    #[arg(long)]
+    pub diff: bool,
+
+    /// Warn about files left with unbalanced braces, brackets or markdown fences by the
+    /// cleaning, as with `check_syntax` in the configuration.
+    #[arg(long)]
+    pub check_syntax: bool,
+
+    /// Make the prompt the same on every machine and every day, to snapshot it in tests, as
+    /// with `deterministic` in the configuration.
+    #[arg(long)]
+    pub deterministic: bool,
+
+    /// Apply the settings of this profile of the project configuration for this run.
+    #[arg(long, value_name = "NAME", conflicts_with = "group")]
+    pub profile: Option<String>,
+
+    /// Layout of the files in text prompts and in the user message of `--format messages` for
+    /// this run, instead of the configured `output_format`. Bundles and JSON documents lay out
+    /// their files on their own and ignore it.
+    #[arg(long, value_enum)]
+    pub output_format: Option<OutputFormat>,
+
+    /// Stop traversal and cleaning after this duration (e.g. `30s`, `500ms`, `2m`) and
+    /// write whatever was processed, marked as partial.
+    #[arg(long, value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+
+    /// Write the prompt to this path instead of the configured output directory. A directory
+    /// (existing, or created when the path ends with a separator) receives the usual
+    /// `project_name_YYYYMMDD.txt` file, `-` is stdout, anything else (e.g. a named pipe) is
+    /// written as is. Status messages then go to stderr.
+    #[arg(long, value_name = "PATH", conflicts_with = "output_fd")]
+    pub output: Option<PathBuf>,
+
+    /// Write the prompt to an already open file descriptor (Unix only), e.g. `--output-fd 3`.
+    /// Status messages then go to stderr.
+    #[arg(long, value_name = "FD", value_parser = clap::value_parser!(i32).range(0..))]
+    pub output_fd: Option<i32>,
+
+    /// Add the configured output directory to the `.gitignore` of the repository when it lies
+    /// inside it and is not listed yet.
+    #[arg(long)]
+    pub gitignore_output: bool,
+
+    /// Also copy the prompt to the clipboard. Prompts over 1 MiB, which clipboards tend to
+    /// truncate silently, are replaced by the path of the prompt file.
+    #[arg(long)]
+    pub clipboard: bool,
+
+    /// Copy the prompt to the clipboard instead of writing a prompt file. Prompts over 1 MiB
+    /// are written to a temporary file, whose path is copied.
+    #[arg(long, conflicts_with_all = ["output", "output_fd", "clipboard"])]
+    pub clipboard_only: bool,
+
+    /// After generating, report how many files each filter rule (deny pattern, extension list,
+    /// changeset, ...) excluded.
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Include configured roots and symbolic links that resolve outside the project directory,
+    /// which are skipped otherwise.
+    #[arg(long)]
+    pub allow_outside_root: bool,
+
+    /// Append the metrics of this run (duration, file and token counts) to this file, as one
+    /// JSON object per line.
+    #[arg(long, value_name = "FILE")]
+    pub metrics_file: Option<PathBuf>,
+
+    /// Add a labeled section to this existing prompt file instead of generating a new prompt,
+    /// with the directories and files given by `--add`.
+    #[arg(long, value_name = "FILE", requires = "add", conflicts_with_all = ["output", "output_fd"])]
+    pub append_to: Option<PathBuf>,
+
+    /// Directory or file to include in the section added with `--append-to` (repeatable).
+    #[arg(long, value_name = "PATH", requires = "append_to")]
+    pub add: Vec<PathBuf>,
+
+    /// Title of the section added with `--append-to`. Defaults to the added paths.
+    #[arg(long, requires = "append_to")]
+    pub label: Option<String>,
+
+    /// Only include the content of the files listed in this JSON file (an array of paths
+    /// relative to the project directory, or an object with a `files` array). The tree still
+    /// shows every allowed file.
+    #[arg(long, value_name = "FILE")]
+    pub files_from_changeset: Option<PathBuf>,
+
+    /// Only include the content of the files changed since this git ref (e.g. `origin/main`),
+    /// committed or not, including untracked files. The tree still shows every allowed file.
+    #[arg(long, value_name = "REF", conflicts_with = "files_from_changeset")]
+    pub since: Option<String>,
+
+    /// Add the `git diff` of the project since this git ref (e.g. `origin/main`) after the
+    /// file contents, as its own fenced section.
+    #[arg(long, value_name = "REF")]
+    pub git_diff: Option<String>,
+
+    /// Only include the files staged in git, followed by the staged diff, with the
+    /// `staged_intro_prompt` of the configuration, for code reviews and commit messages.
+    #[arg(long, conflicts_with_all = ["files_from_changeset", "since", "git_diff"])]
+    pub staged: bool,
+
+    /// Only include the files modified, staged or untracked in git, preceded by the project
+    /// summary written by `prompt-gen summary`, for small and fast everyday prompts.
+    #[arg(long, conflicts_with_all = ["files_from_changeset", "since", "staged"])]
+    pub changed_only: bool,
+
+    /// Build the prompt from the files as they were at this git revision (a commit, branch or
+    /// tag), read from git without checking anything out, e.g. to investigate a regression.
+    #[arg(long, value_name = "REV", conflicts_with_all = ["append_to", "since", "git_diff", "staged", "changed_only"])]
+    pub at: Option<String>,
+
+    /// Build a prompt comparing two git revisions, given as `REV1..REV2`: the files changed
+    /// between them as they are at `REV2`, followed by the diff from `REV1`, e.g. to ask what
+    /// changed and why it might have broken something.
+    #[arg(long, value_name = "REV1..REV2", value_parser = parse_revision_range, conflicts_with_all = ["append_to", "files_from_changeset", "since", "git_diff", "staged", "changed_only", "at"])]
+    pub compare: Option<(String, String)>,
+
+    /// Fill the `{{attachment "NAME"}}` slots of the introductory prompt and the goal with the
+    /// content of this file (repeatable).
+    #[arg(long, value_name = "NAME=PATH", value_parser = parse_attachment)]
+    pub attach: Vec<(String, PathBuf)>,
+
+    /// Build one prompt spanning the projects of this group, created with `prompt-gen group
+    /// create`, each walked with its own configuration.
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["append_to", "output_fd", "clipboard_only", "files_from_changeset", "since", "git_diff", "staged", "changed_only", "at", "compare"])]
+    pub group: Option<String>,
+
+    /// Kind of file the prompt is written as. The files inside text prompts and chat messages
+    /// are laid out as `--output-format` says.
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// List the image files of the project (PNG, JPEG, GIF, WebP, SVG, BMP, ICO) after the tree,
+    /// with their dimensions and size.
+    #[arg(long)]
+    pub with_images: bool,
+
+    /// With `--format messages`, also send the PNG, JPEG, GIF and WebP images of up to 256 kB
+    /// listed by `--with-images` as image parts of the user message, for multimodal models.
+    #[arg(long, requires = "with_images")]
+    pub embed_images: bool,
+
+    /// Experimental: replace the project-specific terms listed in `anonymize_terms` with
+    /// neutral placeholders across the whole prompt.
+    #[arg(long)]
+    pub anonymize: bool,
+
+    /// Also skip directories matching this pattern for this run (repeatable).
+    #[arg(long, value_name = "PATTERN")]
+    pub also_deny: Vec<String>,
+
+    /// Also include files with this extension, or `@group`, for this run (repeatable).
+    #[arg(long, value_name = "EXTENSION")]
+    pub also_allow: Vec<String>,
+
+    /// Store the `--also-deny` and `--also-allow` values in the project configuration.
+    #[arg(long)]
+    pub save: bool,
+
+    /// Leave the configuration file untouched: the goal is not recorded in the history and a
+    /// missing configuration is used without being saved, as with `read_only` in the
+    /// configuration.
+    #[arg(long)]
+    pub no_save_history: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Formats the prompt can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// A single text file with the intro, file contents, tree and goal.
+    Text,
+    /// A zip archive of the cleaned files at their relative paths, plus an `INDEX.md` with
+    /// the intro, tree and goal.
+    Bundle,
+    /// A JSON document with the intro, tree and goal, and the files as `{path, language,
+    /// content, tokens}` objects.
+    Json,
+    /// The `messages` array of a chat completions request: the intro as the system message,
+    /// the tree, files and goal as the user message.
+    Messages,
+}
+
+impl Format {
+    /// Extension of the files written in this format, given the layout of text prompts.
+    pub fn extension(self, output_format: OutputFormat) -> &'static str {
+        match self {
+            Format::Text => output_format.extension(),
+            Format::Bundle => "zip",
+            Format::Json | Format::Messages => "json",
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Serve JSON-RPC 2.0 requests on stdin/stdout, one JSON document per line, for editor
+    /// integrations. Methods: `generate`, `dryRun`, `listProfiles`.
+    Rpc,
+
+    /// Build a minimal prompt for a follow-up instruction, containing only the files of the
+    /// previous prompt that the model's response modified or mentioned.
+    Followup {
+        /// The follow-up instruction, e.g. "now make it async".
+        instruction: String,
+
+        /// File holding the model's response to the previous prompt. Read from stdin when omitted.
+        #[arg(long, value_name = "FILE")]
+        response: Option<PathBuf>,
+    },
+
+    /// Write an `llms.txt`-style project summary (tree, key files, symbol index, README digest)
+    /// meant to be committed with the project and regenerated as it evolves.
+    Summary {
+        /// Where to write the summary. Defaults to `llms.txt` in the current directory.
+        path: Option<PathBuf>,
+    },
+
+    /// Report whether a file would be included in the prompt, and which rule decides it.
+    Explain {
+        /// The file or directory, relative to the project directory.
+        path: PathBuf,
+    },
+
+    /// Manage the reusable instruction fragments of the project, inserted in the goal or the
+    /// introductory prompt with `{{snippet "name"}}`.
+    Snippet {
+        #[command(subcommand)]
+        action: SnippetCommand,
+    },
+
+    /// Show or change the configuration of the project without editing the configuration file
+    /// by hand.
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+
+    /// Manage the configurations stored for project directories, e.g. to clean up directories
+    /// that no longer exist.
+    Projects {
+        #[command(subcommand)]
+        action: ProjectsCommand,
+    },
+
+    /// Manage the groups of configured projects that `--group` assembles into one prompt, for
+    /// questions that cut across repositories.
+    Group {
+        #[command(subcommand)]
+        action: GroupCommand,
+    },
+
+    /// Regenerate the prompt whenever a file it includes is added, removed or modified, so the
+    /// prompt file stays fresh while you code.
+    Watch {
+        /// The goal ending the prompt. Defaults to the last goal of the project history.
+        #[arg(long)]
+        goal: Option<String>,
+
+        /// How often the files are checked where file events are unavailable (e.g. `1s`, `500ms`).
+        #[arg(long, default_value = "1s", value_parser = parse_duration)]
+        interval: Duration,
+
+        /// Write the prompt to this file, or to the usual file inside this directory, instead of
+        /// the configured output directory.
+        #[arg(long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SnippetCommand {
+    /// Store a snippet, replacing any snippet with the same name.
+    Add {
+        name: String,
+
+        /// The snippet text. Read from stdin when omitted.
+        text: Option<String>,
+    },
+
+    /// List the snippets of the project with their first line.
+    List,
+
+    /// Print a snippet, e.g. to paste it or pass it to `--goal`.
+    Use { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the configuration of the project, including the settings inherited from
+    /// `[defaults]` and from the `.prompt-gen.toml` of the repository.
+    Show,
+
+    /// Change a setting of the project, e.g. `config set allowed_extensions rs,toml`. Lists are
+    /// comma-separated.
+    Set { key: String, value: String },
+
+    /// Remove a setting of the project, which then falls back to `[defaults]`.
+    Unset { key: String },
+
+    /// Open the settings of the project in `$VISUAL` or `$EDITOR`, and save them once checked.
+    Edit,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProjectsCommand {
+    /// List the configured project directories with their project names, marking the
+    /// directories that no longer exist.
+    List,
+
+    /// Remove the configuration of a project directory.
+    Remove { dir: PathBuf },
+
+    /// Move the configuration of a project directory to another one, after moving the project.
+    Rename { old: PathBuf, new: PathBuf },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum GroupCommand {
+    /// Store a group, replacing any group with the same name.
+    Create {
+        name: String,
+
+        /// The projects of the group, by project name or directory name (comma-separated).
+        #[arg(long, value_name = "NAMES", value_delimiter = ',', required = true)]
+        projects: Vec<String>,
+    },
+
+    /// List the groups with their projects.
+    List,
+}
+
+/// Parses a duration such as `30s`, `500ms`, `2m` or `1h`. A bare number is read as seconds.
+///
+/// # Arguments
+/// * `value` - The duration as given on the command line.
+///
+/// # Returns
+/// The parsed duration, or a message describing why it is invalid.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let invalid = || format!("invalid duration '{}': expected a number followed by ms, s, m or h", value);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+
+    match unit {
+        "ms" => Ok(Duration::from_millis(amount)),
+        "" | "s" => Ok(Duration::from_secs(amount)),
+        "m" => amount.checked_mul(60).map(Duration::from_secs).ok_or_else(invalid),
+        "h" => amount.checked_mul(3600).map(Duration::from_secs).ok_or_else(invalid),
+        _ => Err(format!("invalid duration unit '{}': expected ms, s, m or h", unit)),
+    }
+}
+
+/// Parses an attachment given as `name=path`.
+pub fn parse_attachment(value: &str) -> Result<(String, PathBuf), String> {
+    match value.split_once('=') {
+        Some((name, path)) if !name.trim().is_empty() && !path.is_empty() => Ok((name.trim().to_string(), PathBuf::from(path))),
+        _ => Err(format!("invalid attachment '{}': expected NAME=PATH", value)),
+    }
+}
+
+/// Parses a `--var NAME=VALUE` argument.
+pub fn parse_var(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.to_string())),
+        _ => Err(format!("invalid variable '{}': expected NAME=VALUE", value)),
+    }
+}
+
+/// Parses a `REV1..REV2` revision range into its two revisions.
+pub fn parse_revision_range(value: &str) -> Result<(String, String), String> {
+    match value.split_once("..") {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() && !to.starts_with('.') => Ok((from.to_string(), to.to_string())),
+        _ => Err(format!("invalid revision range '{}': expected REV1..REV2", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_run_settings() {
+        let cli = Cli::try_parse_from(["prompt-gen", "--goal", "Add a flag", "--allowed-extensions", "rs,toml", "--deny-dirs", "target", "--project-name", "app"]).unwrap();
+        assert_eq!(cli.goal.as_deref(), Some("Add a flag"));
+        assert_eq!(cli.allowed_extensions, Some(vec!["rs".to_string(), "toml".to_string()]));
+        assert_eq!(cli.deny_dirs, Some(vec!["target".to_string()]));
+        assert_eq!(cli.project_name.as_deref(), Some("app"));
+        assert!(Cli::try_parse_from(["prompt-gen", "--goal", "Add a flag", "--goal-file", "goal.md"]).is_err());
+        assert!(Cli::try_parse_from(["prompt-gen", "--goal", "Add a flag", "--template", "bug"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("10 days").is_err());
+        assert!(parse_duration(&format!("{}m", u64::MAX)).is_err());
+        assert!(parse_duration(&format!("{}h", u64::MAX / 60)).is_err());
+    }
+
+    #[test]
+    fn test_parse_attachment() {
+        assert_eq!(parse_attachment("error_log=logs/app.log"), Ok(("error_log".to_string(), PathBuf::from("logs/app.log"))));
+        assert!(parse_attachment("logs/app.log").is_err());
+        assert!(parse_attachment("=logs/app.log").is_err());
+    }
+
+    #[test]
+    fn test_parse_var() {
+        assert_eq!(parse_var("description=crash on empty input"), Ok(("description".to_string(), "crash on empty input".to_string())));
+        assert_eq!(parse_var("file="), Ok(("file".to_string(), String::new())));
+        assert!(parse_var("description").is_err());
+    }
+
+    #[test]
+    fn test_parse_revision_range() {
+        assert_eq!(parse_revision_range("v1.0..HEAD"), Ok(("v1.0".to_string(), "HEAD".to_string())));
+        assert!(parse_revision_range("v1.0").is_err());
+        assert!(parse_revision_range("v1.0..").is_err());
+        assert!(parse_revision_range("v1.0...main").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+// src/clipboard.rs
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Largest prompt put on the clipboard as text. Windows and some X11 and Wayland clipboard
+/// managers truncate or drop much larger contents without reporting an error.
+pub const CLIPBOARD_LIMIT: usize = 1 << 20;
+
+/// What ended up on the clipboard.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Copied {
+    /// The prompt itself.
+    Prompt,
+    /// The path of a file holding the prompt, which was too large for the clipboard.
+    Path(PathBuf),
+}
+
+/// Copies `prompt` to the system clipboard, or the path of a file holding it when it is
+/// larger than [`CLIPBOARD_LIMIT`].
+///
+/// Uses `pbcopy` on macOS, `clip` on Windows, and `wl-copy` (Wayland) or `xclip` elsewhere.
+///
+/// # Arguments
+/// * `prompt` - The prompt text.
+/// * `prompt_path` - The file the prompt was written to, if any. Large prompts without one
+///   are written to a temporary file.
+/// * `file_name` - Name of that temporary file.
+pub fn copy_prompt(prompt: &str, prompt_path: Option<&Path>, file_name: &str) -> io::Result<Copied> {
+    if prompt.len() <= CLIPBOARD_LIMIT {
+        copy(prompt)?;
+        return Ok(Copied::Prompt);
+    }
+    let path = fallback_path(prompt, prompt_path, file_name)?;
+    copy(&path.display().to_string())?;
+    Ok(Copied::Path(path))
+}
+
+/// Returns the file holding a prompt too large for the clipboard, writing it to the temporary
+/// directory when it was not saved anywhere.
+fn fallback_path(prompt: &str, prompt_path: Option<&Path>, file_name: &str) -> io::Result<PathBuf> {
+    match prompt_path {
+        Some(path) => path.canonicalize(),
+        None => {
+            let path = env::temp_dir().join(file_name);
+            fs::write(&path, prompt)?;
+            Ok(path)
+        }
+    }
+}
+
+/// Puts `text` on the clipboard with the platform's clipboard command.
+fn copy(text: &str) -> io::Result<()> {
+    let (program, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+        ("pbcopy", &[])
+    } else if cfg!(windows) {
+        ("clip", &[])
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        ("wl-copy", &[])
+    } else {
+        ("xclip", &["-selection", "clipboard"])
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to run {}: {}", program, e)))?;
+    child.stdin.take().expect("stdin is piped").write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{} exited with {}", program, status)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_path() {
+        let file_name = format!("prompt-gen-test-clipboard-{}.txt", std::process::id());
+        let path = fallback_path("large prompt", None, &file_name).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "large prompt");
+        assert_eq!(fallback_path("large prompt", Some(&path), "unused.txt").unwrap(), path.canonicalize().unwrap());
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+// src/collate.rs
+
+use std::cmp::Ordering;
+
+/// Compares two file names the same way on every platform.
+///
+/// Names are compared after composing decomposed Hangul syllables and kana with voiced sound
+/// marks, as macOS stores them, folding full-width ASCII characters to their ASCII form and
+/// lowercasing. Names equal under these rules are ordered by their bytes, so the order is total.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    key(a).cmp(&key(b)).then_with(|| a.cmp(b))
+}
+
+/// Returns `name` with its decomposed Hangul syllables and voiced kana composed, so that it
+/// renders the same whatever the normalization of the file system.
+pub fn compose(name: &str) -> String {
+    let mut composed = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        let mut c = c;
+        while let Some(combined) = chars.peek().and_then(|&next| combine(c, next)) {
+            c = combined;
+            chars.next();
+        }
+        composed.push(c);
+    }
+    composed
+}
+
+/// Returns the characters `name` is compared by.
+fn key(name: &str) -> Vec<char> {
+    compose(name)
+        .chars()
+        .map(|c| match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Returns the character composed of `c` followed by `next`, if they combine: a Hangul leading
+/// consonant and vowel, a Hangul syllable without a final consonant and one, or a kana and a
+/// combining voiced (゙) or semi-voiced (゚) sound mark.
+fn combine(c: char, next: char) -> Option<char> {
+    let (c, next) = (c as u32, next as u32);
+    let composed = match (c, next) {
+        (0x1100..=0x1112, 0x1161..=0x1175) => 0xAC00 + ((c - 0x1100) * 21 + (next - 0x1161)) * 28,
+        (0xAC00..=0xD7A3, 0x11A8..=0x11C2) if (c - 0xAC00) % 28 == 0 => c + (next - 0x11A7),
+        (_, 0x3099) => voiced(c)?,
+        (_, 0x309A) if is_semi_voiceable(hiragana(c)) => c + 2,
+        _ => return None,
+    };
+    char::from_u32(composed)
+}
+
+/// Returns the voiced form of the kana `c`, e.g. が for か.
+fn voiced(c: u32) -> Option<u32> {
+    match c {
+        0x3046 => Some(0x3094),
+        0x30A6 => Some(0x30F4),
+        0x30EF..=0x30F2 => Some(c + 8),
+        0x309D | 0x30FD => Some(c + 1),
+        _ => {
+            let h = hiragana(c);
+            let voiceable = matches!(h, 0x304B..=0x3061 if h % 2 == 1) || matches!(h, 0x3064 | 0x3066 | 0x3068) || is_semi_voiceable(h);
+            voiceable.then_some(c + 1)
+        }
+    }
+}
+
+/// Returns whether the hiragana `h` takes a semi-voiced sound mark: は, ひ, ふ, へ and ほ.
+fn is_semi_voiceable(h: u32) -> bool {
+    matches!(h, 0x306F | 0x3072 | 0x3075 | 0x3078 | 0x307B)
+}
+
+/// Returns the hiragana of the katakana `c`, or `c` itself.
+fn hiragana(c: u32) -> u32 {
+    match c {
+        0x30A1..=0x30F6 => c - 0x60,
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        // Decomposed as on macOS: ㅎ ㅏ ㄴ, and か with a combining voiced mark
+        let decomposed_hangul = "\u{1112}\u{1161}\u{11AB}.rs";
+        let decomposed_kana = "\u{30AB}\u{3099}\u{30D8}\u{309A}.md";
+        assert_eq!(compose(decomposed_hangul), "한.rs");
+        assert_eq!(compose(decomposed_kana), "ガペ.md");
+
+        let mut names = vec!["b.rs", "한.rs", "README.md", "ＡＰＩ.md", "ガペ.md", "api.rs", decomposed_hangul, decomposed_kana];
+        names.sort_by(|a, b| compare(a, b));
+        assert_eq!(names, vec!["ＡＰＩ.md", "api.rs", "b.rs", "README.md", decomposed_kana, "ガペ.md", decomposed_hangul, "한.rs"]);
+    }
+}
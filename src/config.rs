@@ -1,54 +1,924 @@
 // src/config.rs
 
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::io::{self};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use dirs::home_dir;
+use dirs::{config_dir, home_dir};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use crate::tokens::TokenizerKind;
+use crate::{i18n, pattern};
+
+/// Placeholder of `output_path` replaced with the project name.
+const PROJECT_NAME_PLACEHOLDER: &str = "{project_name}";
+
+/// Patterns of the test fixtures and snapshots collapsed by `collapse_fixtures`.
+const FIXTURE_GLOBS: &[&str] = &["**/fixtures/**", "**/testdata/**", "**/snapshots/**", "**/__snapshots__/**", "**/golden/**", "*.snap"];
+
+/// Build output directories skipped by `deny_build_outputs`, each with the manifests of the
+/// ecosystems producing it. Names also common for source directories, such as `build`, only
+/// count next to one of their manifests, so that a `src/build` module stays; the others count
+/// anywhere.
+const BUILD_OUTPUT_DIRS: &[(&str, &[&str])] = &[
+    ("target", &["Cargo.toml", "pom.xml", "build.sbt"]),
+    ("node_modules", &[]),
+    (".venv", &[]),
+    ("__pycache__", &[]),
+    ("dist", &["package.json", "pyproject.toml", "setup.py", "setup.cfg"]),
+    ("build", &["package.json", "build.gradle", "build.gradle.kts", "settings.gradle", "pyproject.toml", "setup.py", "CMakeLists.txt"]),
+    (".next", &[]),
+    (".terraform", &[]),
+];
+
+/// Directory created in the project for the prompts when `output_path` is empty.
+pub const DEFAULT_OUTPUT_DIR: &str = ".prompts";
+
+/// Number of directory entries walked before giving up, when `max_entries` is unset.
+pub const DEFAULT_MAX_ENTRIES: usize = 50_000;
+
+/// Built-in extension groups, referenced as `@name` in `allowed_extensions`.
+const EXTENSION_GROUPS: &[(&str, &[&str])] = &[
+    ("rust", &["rs", "toml"]),
+    ("web", &["ts", "tsx", "css", "html"]),
+    ("js", &["js", "jsx", "mjs", "cjs"]),
+    ("python", &["py", "pyi"]),
+    ("c", &["c", "h"]),
+    ("cpp", &["cc", "cpp", "cxx", "hh", "hpp", "h"]),
+    ("go", &["go"]),
+    ("java", &["java", "kt"]),
+    ("shell", &["sh", "bash", "zsh"]),
+    ("docs", &["md", "rst", "txt"]),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Config {
     pub project_name: String,
+    /// Directory the prompts are written to. When empty, `.prompts` in the project directory.
     pub output_path: String,
     pub intro_prompt: String,
+    /// Introductory prompt of `--staged` prompts, instead of a built-in one asking for a review
+    /// or a commit message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staged_intro_prompt: Option<String>,
+    /// Closing section of the prompts, after the goal, such as response-format instructions
+    /// ("Answer with unified diffs only").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outro_prompt: Option<String>,
+    /// What happens when the goal is empty.
+    #[serde(default)]
+    pub empty_goal: EmptyGoal,
+    /// Goal used in place of an empty one with `empty_goal = "default"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_goal: Option<String>,
+    /// Extensions of the files to include, or groups of extensions such as `@rust` or `@web`.
     pub allowed_extensions: Vec<String>,
+    /// Extension groups usable as `@name` in `allowed_extensions`, replacing the built-in
+    /// group of the same name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extension_groups: BTreeMap<String, Vec<String>>,
+    /// Names of files to include whatever their extension, for files without one such as
+    /// `Makefile`, `Dockerfile` or `Justfile`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_filenames: Vec<String>,
+    /// Directories to skip: bare names match anywhere, paths such as `docs/generated` or
+    /// `**/snapshots` are matched relative to the project directory.
     pub deny_dirs: Vec<String>,
+    /// Whether well-known build output directories (`target`, `node_modules`, `.venv`,
+    /// `__pycache__`, `dist`, `build`, `.next` and `.terraform`) are skipped even when missing
+    /// from `deny_dirs`. `target`, `dist` and `build` are only skipped next to the manifest of a
+    /// Rust, Java, JavaScript, Python or CMake project, wherever it is in a workspace. On when
+    /// unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deny_build_outputs: Option<bool>,
+    /// Build output directories walked anyway, matched like `deny_dirs`, e.g. a `build`
+    /// directory holding sources.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow_dirs: Vec<String>,
+    /// Additional project roots, relative to the project directory or absolute.
+    /// When empty, the project directory itself is the only root.
+    #[serde(default)]
+    pub roots: Vec<String>,
+    /// Files included only as a skeleton of their declarations and labeled as reference,
+    /// so that models asked for patches leave them alone. Patterns are matched like `deny_dirs`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reference_globs: Vec<String>,
+    /// Whether large test fixtures and snapshots (files under `fixtures`, `testdata`,
+    /// `snapshots`, `__snapshots__` or `golden` directories, `*.snap` files, and those matching
+    /// `fixture_globs`) are collapsed to a one-line note, with a warning. On when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collapse_fixtures: Option<bool>,
+    /// Additional test fixture patterns, matched like `deny_dirs`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixture_globs: Vec<String>,
+    /// Whether files ignored by git (`.gitignore`, `.git/info/exclude` and the global excludes
+    /// file) are skipped. On when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub respect_gitignore: Option<bool>,
+    /// Settings that only apply to files under specific directories.
+    #[serde(default)]
+    pub overrides: Vec<DirOverride>,
+    /// Whether to add a "Build & CI" section with the Makefile, justfile, Dockerfile and
+    /// GitHub workflows, regardless of the allowed extensions.
+    #[serde(default)]
+    pub include_build_files: bool,
+    /// Project-specific terms (company names, product codenames, ...) replaced by neutral
+    /// placeholders when generating with `--anonymize`.
+    #[serde(default)]
+    pub anonymize_terms: Vec<String>,
+    /// How blank lines are handled in file contents.
+    #[serde(default)]
+    pub empty_lines: EmptyLines,
+    /// Blank-line handling for specific file extensions, taking precedence over `empty_lines`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub empty_lines_by_extension: BTreeMap<String, EmptyLines>,
+    /// How much of the files with specific extensions is kept. Files are kept whole by default.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub compression_by_extension: BTreeMap<String, Compression>,
+    /// Whether doc comments (`///`, `//!`, `/** */`, Python docstrings) are kept when
+    /// comments are stripped.
+    #[serde(default)]
+    pub keep_doc_comments: bool,
+    /// How comments are found when they are stripped.
+    #[serde(default)]
+    pub comment_engine: CommentEngine,
+    /// Whether each block of retained lines is preceded by an `[orig lines a-b]` marker
+    /// giving its position in the original file.
+    #[serde(default)]
+    pub annotate_spans: bool,
+    /// Whether a warning is printed for each file whose braces, brackets or markdown fences are
+    /// left unbalanced by the cleaning.
+    #[serde(default)]
+    pub check_syntax: bool,
+    /// Whether credentials found in file contents and diffs (private keys, AWS and API keys,
+    /// values assigned to names such as `password`, high-entropy strings) are replaced with
+    /// `[REDACTED]`, with a warning for each file. On when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redact_secrets: Option<bool>,
+    /// Whether each file header is followed by a `Stats:` line counting its functions,
+    /// types and tests (Rust, Python and C).
+    #[serde(default)]
+    pub file_stats: bool,
+    /// Whether each file header ends with the CRC-32 of the file as read, e.g.
+    /// `File: src/main.rs (crc32 1a2b3c4d)`, to tell whether a patch targets a stale version.
+    #[serde(default)]
+    pub file_hashes: bool,
+    /// Whether prompts are the same on every machine and every day, for snapshot tests: the
+    /// tree starts with `.` instead of the project path, the prompt file name has no date, and
+    /// `--timeout` is ignored.
+    #[serde(default)]
+    pub deterministic: bool,
+    /// Soft-wrap the lines of text prompts longer than this number of columns, ending each
+    /// cut line with `↩`, for chat interfaces that mangle long lines. Bundles are not wrapped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wrap_columns: Option<usize>,
+    /// Layout of text prompts: `plain` (the default) writes `File:` headers and bare fences,
+    /// `markdown` writes `.md` prompts with `### path` headings and language-tagged fences, and
+    /// `xml` wraps each file in `<document>` tags.
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    /// Text written before the content of each file of text prompts instead of the header and
+    /// opening fence of `output_format`, such as `<file path="{path}">`. `{path}`, `{language}`
+    /// and `{crc32}` are replaced with the path of the file, its fence language and its hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_header_template: Option<String>,
+    /// Text written after the content of each file of text prompts instead of the closing fence
+    /// of `output_format`, with the placeholders of `file_header_template`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_footer_template: Option<String>,
+    /// Language of the prompt scaffolding ("Specific Goal:", section headers), as an ISO 639-1
+    /// code such as `fr`. English when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Whether the prompt ends by asking the model to answer in `language`.
+    #[serde(default)]
+    pub answer_in_language: bool,
+    /// Whether the prompt ends by asking the model to answer with a unified diff against the
+    /// file paths of the prompt, ready for `git apply`.
+    #[serde(default)]
+    pub answer_with_diff: bool,
+    /// How tokens are counted: `estimate` (the default), or the `cl100k` and `o200k` BPE
+    /// encodings.
+    #[serde(default)]
+    pub tokenizer: TokenizerKind,
+    /// Largest file, in bytes, whose content is included. Larger files stay in the tree, and
+    /// their content is replaced with a note such as `[skipped: 2.3 MB]`. Unlimited when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_file_bytes: Option<u64>,
+    /// Number of directory entries walked before giving up, as a guard against running in a
+    /// home directory or a disk root. 50 000 when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_entries: Option<usize>,
+    /// Largest prompt allowed, in tokens. Unlimited when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+    /// What happens when a prompt exceeds `max_tokens`.
+    #[serde(default)]
+    pub over_budget: OverBudget,
+    /// Reusable instruction fragments by name, inserted with `{{snippet "name"}}`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub snippets: BTreeMap<String, String>,
+    /// Goals for recurring requests by name, selected with `--template`, such as `bugfix =
+    /// "Fix the following bug: {description}. Include tests."`. The value of each `{name}`
+    /// placeholder is asked for, unless given with `--var`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub goal_templates: BTreeMap<String, String>,
+    /// Named sets of settings applied over the project ones when selected with `--profile`,
+    /// e.g. a `frontend` profile with its own `allowed_extensions`, `deny_dirs` and
+    /// `intro_prompt`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, toml::Table>,
+    /// Whether generating prompts leaves the configuration file untouched, for shared machines
+    /// and read-only home directories: the goals are not recorded in `history`.
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
     pub history: Vec<String>,
 }
 
+/// What happens when a prompt exceeds `max_tokens`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OverBudget {
+    /// Stop without writing the prompt, listing the largest files.
+    #[default]
+    Refuse,
+    /// Leave out the largest files until the prompt fits.
+    Trim,
+}
+
+/// What happens when the goal is empty.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyGoal {
+    /// Stop without writing the prompt.
+    #[default]
+    Reject,
+    /// Use `default_goal` instead.
+    Default,
+    /// Ask for a general review of the project, in the configured language.
+    Review,
+}
+
+/// Layout of text prompts.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// `File: path` headers and bare fences, in a `.txt` file.
+    #[default]
+    Plain,
+    /// `### path` headings and fences tagged with the language of the file, in a `.md` file.
+    Markdown,
+    /// Each file in a `<document>` block with its `<source>` path and `<document_contents>`,
+    /// the packaging recommended for Claude long-context prompts, in a `.txt` file.
+    Xml,
+}
+
+impl OutputFormat {
+    /// Extension of the prompt files written in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Plain | OutputFormat::Xml => "txt",
+            OutputFormat::Markdown => "md",
+        }
+    }
+}
+
+/// How blank lines are handled in file contents once comments are removed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyLines {
+    /// Keep blank lines as they are.
+    Keep,
+    /// Collapse runs of blank lines into a single one.
+    Collapse,
+    /// Remove all blank lines.
+    #[default]
+    Strip,
+}
+
+/// How comments are found when they are stripped.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommentEngine {
+    /// A scan of the characters of the file, aware of the string literals of each language.
+    #[default]
+    Stream,
+    /// The tree-sitter grammar of the language, for Rust, C, C++, Python, JavaScript,
+    /// TypeScript, Go, Java, shell and Ruby files; other files are scanned like with `Stream`.
+    /// Requires the `tree-sitter` feature.
+    TreeSitter,
+}
+
+/// How much of a file is kept in the prompts.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// Keep the whole file.
+    #[default]
+    Full,
+    /// Keep only signatures, type definitions and doc comments, with function bodies replaced
+    /// by `{ ... }` (Rust; other files are kept whole).
+    Signatures,
+    /// Keep only declarations and their doc comments, found line by line, with each run of
+    /// body lines replaced by `...` (Python, Ruby and brace languages such as JavaScript,
+    /// TypeScript, Go, Java and C; other files are kept whole).
+    Outline,
+}
+
+/// Settings scoped to a directory of the project. Unset fields fall back to the
+/// settings of an enclosing override, then to the project defaults.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DirOverride {
+    /// Directory the override applies to, relative to the project directory.
+    pub path: String,
+    /// Whether comments are removed from files under `path`.
+    pub strip_comments: Option<bool>,
+}
+
+impl Config {
+    /// Loads the configuration stored for the project at `project_dir` in the global
+    /// configuration file, merged with the `.prompt-gen.toml` of the repository, if any.
+    pub fn load(project_dir: impl AsRef<Path>) -> Result<Config, Box<dyn std::error::Error>> {
+        let project_dir = project_dir.as_ref().canonicalize()?;
+        load_config(project_dir.to_str().ok_or("the project directory is not valid UTF-8")?)
+    }
+
+    /// Returns the configuration with the settings of the profile `name` applied over the
+    /// project ones. Fails when the project has no such profile or when it sets invalid values.
+    pub fn with_profile(&self, name: &str) -> Result<Config, Box<dyn std::error::Error>> {
+        let Some(profile) = self.profiles.get(name) else {
+            if self.profiles.is_empty() {
+                return Err(format!("no profile named \"{}\", the project has no profiles", name).into());
+            }
+            let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            return Err(format!("no profile named \"{}\", the profiles of the project are {}", name, names.join(", ")).into());
+        };
+        let config = with_defaults(&toml::Value::Table(profile.clone()), Some(&toml::Value::try_from(self)?));
+        let config = config.try_into().map_err(|e: toml::de::Error| format!("invalid profile {}: {}", name, e.message()))?;
+        Ok(config)
+    }
+
+    /// Returns the directory prompts are written to. A leading `~` in `output_path` stands for
+    /// the home directory and `{project_name}` for the project name, so that a single default
+    /// serves every project. Relative paths are relative to `project_dir`, whatever the working
+    /// directory.
+    pub fn output_dir(&self, project_dir: &Path) -> PathBuf {
+        if self.output_path.trim().is_empty() {
+            return project_dir.join(DEFAULT_OUTPUT_DIR);
+        }
+        let output_path = self.output_path.replace(PROJECT_NAME_PLACEHOLDER, &self.project_name);
+        match (output_path.strip_prefix('~'), home_dir()) {
+            (Some(rest), Some(home_dir)) if rest.is_empty() || rest.starts_with(['/', std::path::MAIN_SEPARATOR]) => {
+                home_dir.join(rest.trim_start_matches(['/', std::path::MAIN_SEPARATOR]))
+            }
+            _ => project_dir.join(output_path),
+        }
+    }
+
+    /// Returns the directory prompts are written to, creating it if needed. The default
+    /// `.prompts` directory gets a `.gitignore` so that prompts are never committed.
+    pub fn create_output_dir(&self, project_dir: &Path) -> io::Result<PathBuf> {
+        let output_dir = self.output_dir(project_dir);
+        fs::create_dir_all(&output_dir)?;
+        let gitignore = output_dir.join(".gitignore");
+        if self.output_path.trim().is_empty() && !gitignore.exists() {
+            fs::write(gitignore, "*\n")?;
+        }
+        Ok(output_dir)
+    }
+
+    /// Returns `goal` trimmed, or what `empty_goal` asks for when it is empty. Fails for empty
+    /// goals with `empty_goal = "reject"`, or `"default"` without a `default_goal`.
+    pub fn resolve_goal(&self, goal: &str) -> Result<String, String> {
+        let goal = goal.trim();
+        if !goal.is_empty() {
+            return Ok(goal.to_string());
+        }
+        match self.empty_goal {
+            EmptyGoal::Reject => Err("the goal is empty; enter one, or set empty_goal to \"default\" or \"review\" in the configuration".to_string()),
+            EmptyGoal::Default => match self.default_goal.as_deref().map(str::trim) {
+                Some(default_goal) if !default_goal.is_empty() => Ok(default_goal.to_string()),
+                _ => Err("the goal is empty and empty_goal is \"default\", but no default_goal is configured".to_string()),
+            },
+            EmptyGoal::Review => Ok(self.strings().review_goal.to_string()),
+        }
+    }
+
+    /// Returns the scaffolding strings in the configured language.
+    pub fn strings(&self) -> &'static i18n::Strings {
+        i18n::strings(self.language.as_deref())
+    }
+
+    /// Adds deny patterns and allowed extensions that are not listed yet.
+    pub fn extend_filters(&mut self, deny_dirs: &[String], allowed_extensions: &[String]) {
+        for pattern in deny_dirs {
+            if !self.deny_dirs.contains(pattern) {
+                self.deny_dirs.push(pattern.clone());
+            }
+        }
+        for extension in allowed_extensions {
+            if !self.allowed_extensions.contains(extension) {
+                self.allowed_extensions.push(extension.clone());
+            }
+        }
+    }
+
+    /// Returns whether files with the given extension are included, either listed directly in
+    /// `allowed_extensions` or through an `@group` entry.
+    pub fn is_allowed_extension(&self, extension: &str) -> bool {
+        self.allowed_extensions.iter().any(|allowed| match allowed.strip_prefix('@') {
+            Some(group) => match self.extension_groups.get(group) {
+                Some(extensions) => extensions.iter().any(|e| e == extension),
+                None => EXTENSION_GROUPS
+                    .iter()
+                    .find(|(name, _)| *name == group)
+                    .is_some_and(|(_, extensions)| extensions.contains(&extension)),
+            },
+            None => allowed == extension,
+        })
+    }
+
+    /// Returns whether the file at `path` is included, by its name in `allowed_filenames` or by
+    /// its extension.
+    pub fn is_allowed_file(&self, path: &Path) -> bool {
+        path.file_name().and_then(|name| name.to_str()).is_some_and(|name| self.allowed_filenames.iter().any(|allowed| allowed == name))
+            || path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| self.is_allowed_extension(ext))
+    }
+
+    /// Returns whether the directory at `relative_path` (relative to the project directory)
+    /// matches one of the `deny_dirs` patterns, or is the default output directory.
+    pub fn is_denied_dir(&self, relative_path: &Path) -> bool {
+        self.denying_pattern(relative_path).is_some()
+    }
+
+    /// Returns the `deny_dirs` pattern matching the directory at `relative_path`, or `.prompts`
+    /// for the default output directory.
+    pub fn denying_pattern(&self, relative_path: &Path) -> Option<&str> {
+        if self.output_path.trim().is_empty() && relative_path == Path::new(DEFAULT_OUTPUT_DIR) {
+            return Some(DEFAULT_OUTPUT_DIR);
+        }
+        self.deny_dirs
+            .iter()
+            .find(|pattern| pattern::matches(pattern, relative_path))
+            .map(String::as_str)
+    }
+
+    /// Returns the name of the built-in build output directory at `path`, whose path relative to
+    /// the project directory is `relative_path`, unless `deny_build_outputs` is off or
+    /// `allow_dirs` keeps it.
+    pub fn build_output(&self, path: &Path, relative_path: &Path) -> Option<&'static str> {
+        if !self.deny_build_outputs.unwrap_or(true) || self.allow_dirs.iter().any(|pattern| pattern::matches(pattern, relative_path)) {
+            return None;
+        }
+        let name = path.file_name()?.to_str()?;
+        let &(name, manifests) = BUILD_OUTPUT_DIRS.iter().find(|(dir, _)| *dir == name)?;
+        let parent = path.parent()?;
+        (manifests.is_empty() || manifests.iter().any(|manifest| parent.join(manifest).is_file())).then_some(name)
+    }
+
+    /// Returns whether `file_name` is a prompt or manifest written by prompt-gen for this
+    /// project, such as `project_20240101.txt`, `project_20240101.json` or
+    /// `project_20240101_followup.manifest.json`, or `project.txt` for deterministic prompts.
+    pub fn is_generated_prompt(&self, file_name: &str) -> bool {
+        let Some(rest) = file_name.strip_prefix(&self.project_name) else {
+            return false;
+        };
+        let Some(stem) = rest
+            .strip_suffix(".txt")
+            .or_else(|| rest.strip_suffix(".md"))
+            .or_else(|| rest.strip_suffix(".manifest.json"))
+            .or_else(|| rest.strip_suffix(".json")) else {
+            return false;
+        };
+        if stem.is_empty() {
+            return self.deterministic;
+        }
+        let Some(stem) = stem.strip_prefix('_') else {
+            return false;
+        };
+        let date = stem.strip_suffix("_followup").unwrap_or(stem);
+        date.len() == 8 && date.bytes().all(|byte| byte.is_ascii_digit())
+    }
+
+    /// Returns the number of directory entries walked before giving up.
+    pub fn entry_limit(&self) -> usize {
+        self.max_entries.unwrap_or(DEFAULT_MAX_ENTRIES)
+    }
+
+    /// Returns whether credentials are redacted from the prompt.
+    pub fn redacts_secrets(&self) -> bool {
+        self.redact_secrets.unwrap_or(true)
+    }
+
+    /// Returns whether files ignored by git are skipped.
+    pub fn respects_gitignore(&self) -> bool {
+        self.respect_gitignore.unwrap_or(true)
+    }
+
+    /// Returns whether the file at `relative_path` (relative to the project directory) matches
+    /// one of the `reference_globs` patterns.
+    pub fn is_reference(&self, relative_path: &Path) -> bool {
+        self.reference_globs.iter().any(|pattern| pattern::matches(pattern, relative_path))
+    }
+
+    /// Returns whether the file at `relative_path` (relative to the project directory) is a test
+    /// fixture or snapshot to collapse when large.
+    pub fn collapses_fixture(&self, relative_path: &Path) -> bool {
+        self.collapse_fixtures.unwrap_or(true)
+            && FIXTURE_GLOBS
+                .iter()
+                .copied()
+                .chain(self.fixture_globs.iter().map(String::as_str))
+                .any(|pattern| pattern::matches(pattern, relative_path))
+    }
+
+    /// Returns the blank-line handling for files with the given extension.
+    pub fn empty_lines_for(&self, extension: &str) -> EmptyLines {
+        self.empty_lines_by_extension
+            .get(extension)
+            .copied()
+            .unwrap_or(self.empty_lines)
+    }
+
+    /// Returns how much of the files with the given extension is kept.
+    pub fn compression_for(&self, extension: &str) -> Compression {
+        self.compression_by_extension.get(extension).copied().unwrap_or_default()
+    }
+
+    /// Returns whether comments should be removed from the file at `relative_path`
+    /// (relative to the project directory). The most specific override wins.
+    pub fn strip_comments_for(&self, relative_path: &Path) -> bool {
+        self.overrides
+            .iter()
+            .filter(|o| relative_path.starts_with(&o.path))
+            .filter_map(|o| o.strip_comments.map(|strip| (Path::new(&o.path).components().count(), strip)))
+            .max_by_key(|(depth, _)| *depth)
+            .map(|(_, strip)| strip)
+            .unwrap_or(true)
+    }
+}
+
+/// Directory of the global configuration file, in the platform configuration directory.
+const CONFIG_DIR: &str = "prompt-gen";
+
+/// Returns the path of the global configuration file: `prompt-gen/config.toml` in the platform
+/// configuration directory (`$XDG_CONFIG_HOME`, or `~/.config`, on Linux).
+///
+/// A `~/.prompt-gen.toml` written by earlier versions keeps being used until
+/// [`migrate_legacy_config`] moves it.
 pub fn get_config_path() -> Option<PathBuf> {
-    if let Some(home_dir) = home_dir() {
-        let suffix = env::var("CONFIG_TEST_SUFFIX").unwrap_or_default();
-        let config_filename = format!(".prompt-gen{}.toml", suffix);
-        let config_path = home_dir.join(config_filename);
-        println!("Config Path: {}", config_path.display());
-        Some(config_path)
-    } else {
-        None
+    let (config_path, legacy_path) = config_paths()?;
+    match legacy_path {
+        Some(legacy_path) if !config_path.exists() && legacy_path.is_file() => Some(legacy_path),
+        _ => Some(config_path),
+    }
+}
+
+/// Moves the `~/.prompt-gen.toml` written by earlier versions to `prompt-gen/config.toml` in the
+/// platform configuration directory, unless a configuration is already there.
+///
+/// # Returns
+/// The paths the configuration was moved from and to, or `None` when there was nothing to move.
+pub fn migrate_legacy_config() -> io::Result<Option<(PathBuf, PathBuf)>> {
+    let Some((config_path, Some(legacy_path))) = config_paths() else {
+        return Ok(None);
+    };
+    if config_path.exists() || !legacy_path.is_file() {
+        return Ok(None);
+    }
+    migrate_config(&legacy_path, &config_path)?;
+    Ok(Some((legacy_path, config_path)))
+}
+
+/// Returns the path of the global configuration file and the one of earlier versions, in the
+/// home directory.
+fn config_paths() -> Option<(PathBuf, Option<PathBuf>)> {
+    let suffix = env::var("CONFIG_TEST_SUFFIX").unwrap_or_default();
+    let config_path = config_dir()?.join(CONFIG_DIR).join(format!("config{}.toml", suffix));
+    let legacy_path = home_dir().map(|home_dir| home_dir.join(format!(".prompt-gen{}.toml", suffix)));
+    Some((config_path, legacy_path))
+}
+
+/// Moves the configuration file at `legacy_path` to `config_path`, copying it when they are on
+/// different file systems.
+fn migrate_config(legacy_path: &Path, config_path: &Path) -> io::Result<()> {
+    if let Some(dir) = config_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    if fs::rename(legacy_path, config_path).is_err() {
+        fs::copy(legacy_path, config_path)?;
+        fs::remove_file(legacy_path)?;
+    }
+    Ok(())
+}
+
+/// Name of the table holding the settings inherited by every project.
+pub const DEFAULTS_KEY: &str = "defaults";
+
+/// Returns the project settings completed with the values of the `[defaults]` table
+/// for the keys the project does not set.
+fn with_defaults(project_config: &toml::Value, defaults: Option<&toml::Value>) -> toml::Value {
+    let mut project_config = project_config.clone();
+    if let (Some(project_table), Some(toml::Value::Table(defaults))) = (project_config.as_table_mut(), defaults) {
+        for (key, value) in defaults {
+            project_table.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+    project_config
+}
+
+/// Removes the settings equal to the `[defaults]` table, so that they are only stored once.
+fn without_defaults(mut project_config: toml::Value, defaults: Option<&toml::Value>) -> toml::Value {
+    if let (Some(project_table), Some(toml::Value::Table(defaults))) = (project_config.as_table_mut(), defaults) {
+        project_table.retain(|key, value| defaults.get(key) != Some(value));
+    }
+    project_config
+}
+
+/// Settings without a default value, which every project sets.
+const REQUIRED_KEYS: &[&str] = &["project_name", "output_path", "intro_prompt", "allowed_extensions", "deny_dirs"];
+
+/// Completes the required settings that neither the project nor `[defaults]` set with the
+/// values detected in the project directory, as for a project configured without questions, so
+/// that a `.prompt-gen.toml` setting only a few keys is enough.
+fn with_detected_defaults(project_config: toml::Value, project_dir: &Path) -> Result<toml::Value, Box<dyn std::error::Error>> {
+    let is_complete = project_config.as_table().is_some_and(|table| REQUIRED_KEYS.iter().all(|key| table.contains_key(*key)));
+    if is_complete {
+        return Ok(project_config);
+    }
+    let detected = toml::Value::try_from(crate::init::detected_defaults(project_dir))?;
+    let detected: toml::Table = REQUIRED_KEYS
+        .iter()
+        .filter_map(|key| Some((key.to_string(), detected.get(*key)?.clone())))
+        .collect();
+    Ok(with_defaults(&project_config, Some(&toml::Value::Table(detected))))
+}
+
+/// Name of the project configuration file, committed at the root of a repository.
+pub const PROJECT_CONFIG_FILE: &str = ".prompt-gen.toml";
+
+/// Returns the path of the project configuration file applying to `current_dir`: the one in
+/// `current_dir` or in a parent directory up to the root of its git repository. The global
+/// configuration file is never taken for a project one.
+fn project_config_path(current_dir: &str, global_path: &Path) -> Option<PathBuf> {
+    let current_dir = Path::new(current_dir);
+    let repo_root = current_dir.ancestors().find(|dir| dir.join(".git").exists()).unwrap_or(current_dir);
+    current_dir
+        .ancestors()
+        .take_while(|dir| dir.starts_with(repo_root))
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file() && path != global_path)
+}
+
+/// Reads the project configuration file applying to `current_dir`, if any.
+fn read_project_config(current_dir: &str, global_path: &Path) -> Result<Option<toml::Value>, Box<dyn std::error::Error>> {
+    match project_config_path(current_dir, global_path) {
+        Some(path) => {
+            let content = fs::read_to_string(&path)?;
+            let table: toml::Table = toml::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+            Ok(Some(toml::Value::Table(table)))
+        }
+        None => Ok(None),
     }
 }
 
+/// Loads the configuration of the project at `current_dir`.
+///
+/// The settings of the `.prompt-gen.toml` committed in the repository win over the ones stored
+/// for the project in the global configuration file, which win over its `[defaults]` table.
 pub fn load_config(current_dir: &str) -> Result<Config, Box<dyn std::error::Error>> {
     if let Some(config_path) = get_config_path() {
-        if config_path.exists() {
-            let config_content = fs::read_to_string(config_path)?;
-            println!("Read TOML from file: {}", config_content);  // Print the content read from file
-            let config_table: toml::Table = toml::from_str(&config_content)?;
-
-            if let Some(project_config) = config_table.get(current_dir) {
-                let project_config: Config = project_config.clone().try_into()?;
-                Ok(project_config)
-            } else {
-                Err(format!("Configuration not found for directory: {}", current_dir).into())
-            }
+        let config_table: toml::Table = if config_path.exists() {
+            let config_content = fs::read_to_string(&config_path)?;
+            toml::from_str(&config_content)?
         } else {
-            Err("Configuration file not found.".into())
+            toml::Table::new()
+        };
+        let project_file = read_project_config(current_dir, &config_path)?;
+
+        let project_config = match (config_table.get(current_dir), &project_file) {
+            (Some(project_config), _) => project_config.clone(),
+            (None, Some(_)) => toml::Value::Table(toml::Table::new()),
+            (None, None) if config_path.exists() => return Err(format!("Configuration not found for directory: {}", current_dir).into()),
+            (None, None) => return Err("Configuration file not found.".into()),
+        };
+        let mut project_config = with_defaults(&project_config, config_table.get(DEFAULTS_KEY));
+        if let Some(project_file) = &project_file {
+            project_config = with_defaults(project_file, Some(&project_config));
         }
+        let project_config: Config = with_detected_defaults(project_config, Path::new(current_dir))?.try_into()?;
+        Ok(project_config)
     } else {
-        Err("Home directory not found.".into())
+        Err("Configuration directory not found.".into())
+    }
+}
+
+/// Name of the table holding the project groups, each listing the names of its projects.
+pub const GROUPS_KEY: &str = "groups";
+
+/// Reads the whole configuration file, or an empty table when there is none yet.
+fn read_config_table() -> Result<(PathBuf, toml::Table), Box<dyn std::error::Error>> {
+    let config_path = get_config_path().ok_or("Configuration directory not found.")?;
+    let config_table = if config_path.exists() {
+        toml::from_str(&fs::read_to_string(&config_path)?)?
+    } else {
+        toml::Table::new()
+    };
+    Ok((config_path, config_table))
+}
+
+/// Finds the configured project called `name`, matching its project name or the name of its
+/// directory, and returns its directory and configuration.
+fn find_project(config_table: &toml::Table, name: &str) -> Result<(PathBuf, Config), Box<dyn std::error::Error>> {
+    let defaults = config_table.get(DEFAULTS_KEY);
+    for (project_dir, project_config) in config_table.iter().filter(|(key, _)| *key != DEFAULTS_KEY && *key != GROUPS_KEY) {
+        let Ok(config) = with_defaults(project_config, defaults).try_into::<Config>() else {
+            continue;
+        };
+        if config.project_name == name || Path::new(project_dir).file_name().is_some_and(|dir_name| dir_name == name) {
+            return Ok((PathBuf::from(project_dir), config));
+        }
+    }
+    Err(format!("no configured project named \"{}\"", name).into())
+}
+
+/// Stores the group `name` of the configured `projects`, replacing any group with the same
+/// name. Fails when one of the projects is not configured.
+pub fn save_group(name: &str, projects: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (config_path, mut config_table) = read_config_table()?;
+    for project in projects {
+        find_project(&config_table, project)?;
+    }
+    let groups = config_table.entry(GROUPS_KEY).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    let groups = groups.as_table_mut().ok_or("the groups entry of the configuration is not a table")?;
+    groups.insert(name.to_string(), toml::Value::try_from(projects)?);
+    fs::write(config_path, toml::to_string(&config_table)?)?;
+    Ok(())
+}
+
+/// Returns the groups of the configuration file with the names of their projects.
+fn groups(config_table: &toml::Table) -> Result<BTreeMap<String, Vec<String>>, Box<dyn std::error::Error>> {
+    match config_table.get(GROUPS_KEY) {
+        Some(groups) => Ok(groups.clone().try_into()?),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+/// Returns the groups with the names of their projects.
+pub fn load_groups() -> Result<BTreeMap<String, Vec<String>>, Box<dyn std::error::Error>> {
+    groups(&read_config_table()?.1)
+}
+
+/// Returns the directory and configuration of each project of the group `name`, in the order
+/// of the group.
+pub fn load_group(name: &str) -> Result<Vec<(PathBuf, Config)>, Box<dyn std::error::Error>> {
+    let (_, config_table) = read_config_table()?;
+    let projects = groups(&config_table)?
+        .remove(name)
+        .ok_or_else(|| format!("no group named \"{}\", create it with prompt-gen group create {} --projects NAMES", name, name))?;
+    projects.iter().map(|project| find_project(&config_table, project)).collect()
+}
+
+/// Returns the directories of the configured projects with their project names, as stored in
+/// the configuration file.
+pub fn list_projects() -> Result<Vec<(PathBuf, String)>, Box<dyn std::error::Error>> {
+    let (_, config_table) = read_config_table()?;
+    Ok(config_table
+        .iter()
+        .filter(|(key, _)| *key != DEFAULTS_KEY && *key != GROUPS_KEY)
+        .map(|(project_dir, section)| {
+            let project_name = section.get("project_name").and_then(toml::Value::as_str).unwrap_or_default();
+            (PathBuf::from(project_dir), project_name.to_string())
+        })
+        .collect())
+}
+
+/// Removes the configuration of the project at `project_dir`. Fails when it is not configured.
+pub fn remove_project(project_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let (config_path, mut config_table) = read_config_table()?;
+    if project_dir == DEFAULTS_KEY || project_dir == GROUPS_KEY || config_table.remove(project_dir).is_none() {
+        return Err(format!("no configuration for {}", project_dir).into());
+    }
+    fs::write(config_path, toml::to_string(&config_table)?)?;
+    Ok(())
+}
+
+/// Moves the configuration of the project at `old_dir` to `new_dir`, for a project whose
+/// directory was moved. Fails when `old_dir` is not configured or `new_dir` already is.
+pub fn rename_project(old_dir: &str, new_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let (config_path, mut config_table) = read_config_table()?;
+    if [DEFAULTS_KEY, GROUPS_KEY].contains(&new_dir) || config_table.contains_key(new_dir) {
+        return Err(format!("{} is already configured, remove it first", new_dir).into());
+    }
+    let section = match config_table.remove(old_dir) {
+        Some(section) if old_dir != DEFAULTS_KEY && old_dir != GROUPS_KEY => section,
+        _ => return Err(format!("no configuration for {}", old_dir).into()),
+    };
+    config_table.insert(new_dir.to_string(), section);
+    fs::write(config_path, toml::to_string(&config_table)?)?;
+    Ok(())
+}
+
+/// Returns the settings stored for the project at `current_dir` in the configuration file,
+/// without the inherited ones.
+pub fn project_section(current_dir: &str) -> Result<toml::Table, Box<dyn std::error::Error>> {
+    let (_, config_table) = read_config_table()?;
+    match config_table.get(current_dir) {
+        Some(toml::Value::Table(section)) => Ok(section.clone()),
+        _ => Err(format!("no configuration for {}, run prompt-gen once to create it", current_dir).into()),
+    }
+}
+
+/// Replaces the settings stored for the project at `current_dir` with `section`, once checked
+/// to make a valid configuration with the inherited settings.
+pub fn save_project_section(current_dir: &str, section: toml::Table) -> Result<(), Box<dyn std::error::Error>> {
+    let (config_path, mut config_table) = read_config_table()?;
+    let section = toml::Value::Table(section);
+    let mut project_config = with_defaults(&section, config_table.get(DEFAULTS_KEY));
+    if let Some(project_file) = read_project_config(current_dir, &config_path)? {
+        project_config = with_defaults(&project_file, Some(&project_config));
+    }
+    project_config.try_into::<Config>().map_err(|e| format!("invalid configuration: {}", e.message()))?;
+    config_table.insert(current_dir.to_string(), section);
+    fs::write(config_path, toml::to_string(&config_table)?)?;
+    Ok(())
+}
+
+/// Sets the setting `key` of the project at `current_dir` to `value`, read with the type of the
+/// setting: a comma-separated list for lists, `true` or `false` for flags, a number, or text.
+/// Fails for unknown settings and values of the wrong type.
+pub fn set_setting(current_dir: &str, key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let config = load_config(current_dir)?;
+    let current = toml::Value::try_from(&config)?;
+    let value = setting_value(current.get(key), value);
+
+    // Unknown settings are dropped when the configuration is read back
+    let mut updated = current.as_table().cloned().unwrap_or_default();
+    updated.insert(key.to_string(), value.clone());
+    let updated: Config = toml::Value::Table(updated).try_into().map_err(|e: toml::de::Error| format!("invalid value for {}: {}", key, e.message()))?;
+    if toml::Value::try_from(&updated)?.get(key) != Some(&value) {
+        return Err(format!("unknown setting {}", key).into());
+    }
+
+    let mut section = project_section(current_dir)?;
+    section.insert(key.to_string(), value);
+    save_project_section(current_dir, section)?;
+    let config_path = get_config_path().ok_or("Configuration directory not found.")?;
+    if read_project_config(current_dir, &config_path)?.is_some_and(|project_file| project_file.get(key).is_some()) {
+        eprintln!("Warning: the {} of the repository also sets {}, and its value wins.", PROJECT_CONFIG_FILE, key);
+    }
+    Ok(())
+}
+
+/// Removes the setting `key` of the project at `current_dir`, which then falls back to the
+/// `[defaults]` table. Fails when the setting is required.
+pub fn unset_setting(current_dir: &str, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut section = project_section(current_dir)?;
+    if section.remove(key).is_none() {
+        return Err(format!("{} is not set for this project", key).into());
+    }
+    save_project_section(current_dir, section)
+}
+
+/// Reads `value`, given on the command line, with the type of the `current` value of the
+/// setting. Settings without a value yet are read as TOML when possible, as text otherwise.
+fn setting_value(current: Option<&toml::Value>, value: &str) -> toml::Value {
+    let parsed = || toml::from_str::<toml::Table>(&format!("value = {}", value)).ok().and_then(|mut table| table.remove("value"));
+    match current {
+        Some(toml::Value::Array(_)) => toml::Value::Array(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| toml::Value::String(item.to_string()))
+                .collect(),
+        ),
+        Some(toml::Value::String(_)) => toml::Value::String(value.to_string()),
+        _ => parsed().unwrap_or_else(|| toml::Value::String(value.to_string())),
+    }
+}
+
+/// Opens the settings stored for the project at `current_dir` in `$VISUAL` or `$EDITOR` (`vi`
+/// when neither is set), and saves them once the editor exits, when they are valid.
+pub fn edit_project_section(current_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let section = project_section(current_dir)?;
+    let path = env::temp_dir().join(format!("prompt-gen-config-{}.toml", std::process::id()));
+    fs::write(&path, toml::to_string(&section)?)?;
+
+    let editor = env::var("VISUAL").or_else(|_| env::var("EDITOR")).unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().ok_or("the editor command is empty")?;
+    let status = std::process::Command::new(program).args(words).arg(&path).status();
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    if !status?.success() {
+        return Err(format!("{} failed, the configuration is unchanged", editor).into());
     }
+    let section: toml::Table = toml::from_str(&edited?).map_err(|e| format!("invalid TOML, the configuration is unchanged: {}", e.message()))?;
+    save_project_section(current_dir, section)
 }
 
 pub fn create_config<R, W>(current_dir: &str, mut reader: R, mut writer: W) -> Result<Config, Box<dyn std::error::Error>>
@@ -70,7 +940,7 @@ pub fn create_config<R, W>(current_dir: &str, mut reader: R, mut writer: W) -> R
         project_name.to_string()
     };
 
-    write!(writer, "Enter the output path: ")?;
+    write!(writer, "Enter the output path (default: {} in the project directory): ", DEFAULT_OUTPUT_DIR)?;
     writer.flush()?;
     let mut output_path = String::new();
     reader.read_line(&mut output_path)?;
@@ -108,7 +978,7 @@ pub fn create_config<R, W>(current_dir: &str, mut reader: R, mut writer: W) -> R
         intro_prompt,
         allowed_extensions,
         deny_dirs,
-        history: Vec::new(),
+        ..Default::default()
     };
 
     Ok(config)
@@ -123,15 +993,22 @@ pub fn save_config(config: &Config, current_dir: &str) -> Result<(), Box<dyn std
             toml::Table::new()
         };
 
-        let config_value = toml::Value::try_from(config)?;
+        // Settings coming from the project configuration file stay there
+        let mut config_value = toml::Value::try_from(config)?;
+        if let Some(project_file) = read_project_config(current_dir, &config_path)? {
+            config_value = without_defaults(config_value, Some(&project_file));
+        }
+        let config_value = without_defaults(config_value, config_content.get(DEFAULTS_KEY));
         config_content.insert(current_dir.to_string(), config_value);
 
         let config_str = toml::to_string(&config_content)?;
-        println!("Writing TOML to file: {}", config_str);  // Print the TOML string being written
+        if let Some(dir) = config_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
         fs::write(config_path, config_str)?;
         Ok(())
     } else {
-        Err("Home directory not found.".into())
+        Err("Configuration directory not found.".into())
     }
 }
 
@@ -140,12 +1017,19 @@ mod tests {
     use std::fs;
 
     use super::*;
+    use crate::testing::TempDir;
+
+    /* The environment variable is shared by the whole process, so tests changing it run one at a time. */
+    static TEST_ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
 
     /* This closure allows to have test using a different toml file, this is important because cargo test default to being multithreaded and concurrent access to a file would fail. */
-    fn with_test_env<F: FnOnce() -> ()>(test_name: &str, test: F) {
+    fn with_test_env<F: FnOnce()>(test_name: &str, test: F) {
+        let _lock = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
         // Setup: Set the environment variable
         std::env::remove_var("CONFIG_TEST_SUFFIX");
         std::env::set_var("CONFIG_TEST_SUFFIX", format!("-{}", test_name));
+        fs::create_dir_all(get_config_path().unwrap().parent().unwrap()).unwrap();
 
         // Run the test
         test();
@@ -156,11 +1040,37 @@ mod tests {
 
     #[test]
     fn test_get_config_path() {
-        let config_path = get_config_path();
-        assert!(config_path.is_some());
-        let config_path = config_path.unwrap();
-        println!("Config path: {}", config_path.display());
-        assert!(config_path.ends_with(".prompt-gen.toml"));
+        with_test_env("test_get_config_path", || {
+            let config_path = get_config_path().unwrap();
+            assert!(config_path.ends_with("prompt-gen/config-test_get_config_path.toml"));
+        });
+    }
+
+    #[test]
+    fn test_migrate_config() {
+        with_test_env("test_migrate_config", || {
+            let config_path = get_config_path().unwrap();
+            let _ = fs::remove_file(&config_path);
+            let legacy_path = home_dir().unwrap().join(".prompt-gen-test_migrate_config.toml");
+            fs::write(&legacy_path, "[defaults]\nlanguage = \"fr\"\n").unwrap();
+
+            // The file of earlier versions is used as is until it is moved
+            assert_eq!(get_config_path().unwrap(), legacy_path);
+            assert!(!config_path.exists());
+            assert_eq!(migrate_legacy_config().unwrap(), Some((legacy_path.clone(), config_path.clone())));
+            assert_eq!(get_config_path().unwrap(), config_path);
+            assert_eq!(fs::read_to_string(&config_path).unwrap(), "[defaults]\nlanguage = \"fr\"\n");
+            assert!(!legacy_path.exists());
+
+            // Once moved, it is left alone
+            fs::write(&legacy_path, "").unwrap();
+            assert_eq!(migrate_legacy_config().unwrap(), None);
+            assert_eq!(get_config_path().unwrap(), config_path);
+            assert!(legacy_path.exists());
+
+            fs::remove_file(legacy_path).unwrap();
+            fs::remove_file(config_path).unwrap();
+        });
     }
 
     #[test]
@@ -237,7 +1147,7 @@ mod tests {
             assert!(output.contains("Configuration not found for the current directory."));
             assert!(output.contains("Let's create a new configuration."));
             assert!(output.contains(&format!("Enter the project name (default: {}): ", current_dir)));
-            assert!(output.contains("Enter the output path: "));
+            assert!(output.contains("Enter the output path (default: .prompts in the project directory): "));
             assert!(output.contains("Enter the introductory prompt: "));
             assert!(output.contains("Enter the allowed file extensions (comma-separated): "));
             assert!(output.contains("Enter the directories to ignore (comma-separated): "));
@@ -248,6 +1158,465 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_strip_comments_for() {
+        let config_content = r#"
+            project_name = "Generated"
+            output_path = "/path/to/output"
+            intro_prompt = "Intro"
+            allowed_extensions = ["rs"]
+            deny_dirs = []
+            history = []
+
+            [[overrides]]
+            path = "src/generated"
+            strip_comments = false
+
+            [[overrides]]
+            path = "src/generated/internal"
+            strip_comments = true
+        "#;
+        let config: Config = toml::from_str(config_content).unwrap();
+
+        assert!(config.strip_comments_for(Path::new("src/main.rs")));
+        assert!(!config.strip_comments_for(Path::new("src/generated/api.rs")));
+        assert!(config.strip_comments_for(Path::new("src/generated/internal/impl.rs")));
+        // Matching is done on whole path components
+        assert!(config.strip_comments_for(Path::new("src/generated_old/api.rs")));
+    }
+
+    #[test]
+    fn test_empty_lines_for() {
+        let config_content = r#"
+            project_name = "Polyglot"
+            output_path = "/path/to/output"
+            intro_prompt = "Intro"
+            allowed_extensions = ["rs", "py"]
+            deny_dirs = []
+            history = []
+            empty_lines = "keep"
+
+            [empty_lines_by_extension]
+            py = "collapse"
+        "#;
+        let config: Config = toml::from_str(config_content).unwrap();
+        assert_eq!(config.empty_lines_for("rs"), EmptyLines::Keep);
+        assert_eq!(config.empty_lines_for("py"), EmptyLines::Collapse);
+
+        // Blank lines are stripped unless configured otherwise
+        let config_content = r#"
+            project_name = "Default"
+            output_path = "/path/to/output"
+            intro_prompt = "Intro"
+            allowed_extensions = ["rs"]
+            deny_dirs = []
+            history = []
+        "#;
+        let config: Config = toml::from_str(config_content).unwrap();
+        assert_eq!(config.empty_lines_for("rs"), EmptyLines::Strip);
+    }
+
+    #[test]
+    fn test_collapses_fixture() {
+        let mut config = Config::default();
+        assert!(config.collapses_fixture(Path::new("tests/fixtures/response.json")));
+        assert!(config.collapses_fixture(Path::new("src/__snapshots__/app.test.ts.snap")));
+        assert!(!config.collapses_fixture(Path::new("tests/api.rs")));
+        assert!(!config.collapses_fixture(Path::new("tests/data/big.csv")));
+
+        config.fixture_globs = vec!["tests/data/**".to_string()];
+        assert!(config.collapses_fixture(Path::new("tests/data/big.csv")));
+        config.collapse_fixtures = Some(false);
+        assert!(!config.collapses_fixture(Path::new("tests/fixtures/response.json")));
+    }
+
+    #[test]
+    fn test_build_output() {
+        let project_dir = TempDir::new("build-output");
+        fs::create_dir_all(project_dir.join("crates/app")).unwrap();
+        fs::write(project_dir.join("crates/app/Cargo.toml"), "[package]\n").unwrap();
+        let mut config = Config::default();
+
+        let build_output = |config: &Config, relative_path: &str| config.build_output(&project_dir.join(relative_path), Path::new(relative_path));
+        assert_eq!(build_output(&config, "web/node_modules"), Some("node_modules"));
+        assert_eq!(build_output(&config, "crates/app/target"), Some("target"));
+        // Without a manifest next to it, `build` is a source directory
+        assert_eq!(build_output(&config, "src/build"), None);
+        assert_eq!(build_output(&config, "src"), None);
+
+        config.allow_dirs = vec!["web/node_modules".to_string()];
+        assert_eq!(build_output(&config, "web/node_modules"), None);
+        config.deny_build_outputs = Some(false);
+        assert_eq!(build_output(&config, "crates/app/target"), None);
+    }
+
+    #[test]
+    fn test_output_dir() {
+        let project_dir = TempDir::new("output-dir");
+        let mut config = Config::default();
+        let output_dir = config.create_output_dir(&project_dir).unwrap();
+        assert_eq!(output_dir, project_dir.join(".prompts"));
+        assert_eq!(fs::read_to_string(output_dir.join(".gitignore")).unwrap(), "*\n");
+        assert!(config.is_denied_dir(Path::new(".prompts")));
+
+        config.output_path = "/path/to/output".to_string();
+        assert_eq!(config.output_dir(&project_dir), Path::new("/path/to/output"));
+        assert!(!config.is_denied_dir(Path::new(".prompts")));
+
+        config.project_name = "app".to_string();
+        config.output_path = "~/prompts/{project_name}".to_string();
+        assert_eq!(config.output_dir(&project_dir), home_dir().unwrap().join("prompts/app"));
+        config.output_path = "/path/to/~user/{project_name}".to_string();
+        assert_eq!(config.output_dir(&project_dir), Path::new("/path/to/~user/app"));
+        // Relative paths do not depend on the working directory
+        config.output_path = "prompts/{project_name}".to_string();
+        assert_eq!(config.output_dir(&project_dir), project_dir.join("prompts/app"));
+        assert!(config.is_generated_prompt("app_20240101.txt"));
+        assert!(config.is_generated_prompt("app_20240101_followup.manifest.json"));
+        assert!(config.is_generated_prompt("app_20240101.md"));
+        assert!(!config.is_generated_prompt("app_notes.txt"));
+        assert!(!config.is_generated_prompt("other_20240101.txt"));
+        assert!(!config.is_generated_prompt("app.txt"));
+        assert!(Config { deterministic: true, ..config.clone() }.is_generated_prompt("app.txt"));
+    }
+
+    #[test]
+    fn test_is_allowed_extension() {
+        let config_content = r#"
+            project_name = "Groups"
+            output_path = "/path/to/output"
+            intro_prompt = "Intro"
+            allowed_extensions = ["@rust", "@web", "proto"]
+            allowed_filenames = ["Makefile", "Dockerfile"]
+            deny_dirs = []
+            history = []
+
+            [extension_groups]
+            web = ["ts", "vue"]
+        "#;
+        let config: Config = toml::from_str(config_content).unwrap();
+        assert!(config.is_allowed_extension("rs"));
+        assert!(config.is_allowed_extension("toml"));
+        assert!(config.is_allowed_extension("proto"));
+        // The configured group replaces the built-in one
+        assert!(config.is_allowed_extension("vue"));
+        assert!(!config.is_allowed_extension("css"));
+        assert!(!config.is_allowed_extension("py"));
+        // Files are allowed by name or by extension
+        assert!(config.is_allowed_file(Path::new("build/Makefile")));
+        assert!(config.is_allowed_file(Path::new("src/main.rs")));
+        assert!(!config.is_allowed_file(Path::new("LICENSE")));
+        assert!(!config.is_allowed_file(Path::new("Makefile.bak")));
+    }
+
+    #[test]
+    fn test_resolve_goal() {
+        let mut config = Config::default();
+        assert_eq!(config.resolve_goal("  Add a flag\n"), Ok("Add a flag".to_string()));
+        assert!(config.resolve_goal("").is_err());
+        assert!(config.resolve_goal(" \n").is_err());
+
+        config.empty_goal = EmptyGoal::Default;
+        assert!(config.resolve_goal("").is_err());
+        config.default_goal = Some("Fix the failing tests".to_string());
+        assert_eq!(config.resolve_goal(""), Ok("Fix the failing tests".to_string()));
+        assert_eq!(config.resolve_goal("Add a flag"), Ok("Add a flag".to_string()));
+
+        config.empty_goal = EmptyGoal::Review;
+        config.language = Some("fr".to_string());
+        assert_eq!(config.resolve_goal(""), Ok(i18n::strings(Some("fr")).review_goal.to_string()));
+    }
+
+    #[test]
+    fn test_with_profile() {
+        let config_content = r#"
+            project_name = "Monorepo"
+            output_path = ""
+            intro_prompt = "Intro"
+            allowed_extensions = ["rs", "ts"]
+            deny_dirs = ["target"]
+            history = []
+
+            [profiles.frontend]
+            allowed_extensions = ["ts", "css"]
+            intro_prompt = "Frontend intro"
+
+            [profiles.broken]
+            max_tokens = "many"
+        "#;
+        let config: Config = toml::from_str(config_content).unwrap();
+        let frontend = config.with_profile("frontend").unwrap();
+        assert_eq!(frontend.allowed_extensions, vec!["ts", "css"]);
+        assert_eq!(frontend.intro_prompt, "Frontend intro");
+        // Settings the profile does not set are the project ones
+        assert_eq!(frontend.deny_dirs, vec!["target"]);
+        assert!(config.with_profile("broken").is_err());
+        let error = config.with_profile("docs").unwrap_err().to_string();
+        assert!(error.contains("broken, frontend"));
+    }
+
+    #[test]
+    fn test_load_config_with_defaults() {
+        with_test_env("test_load_config_with_defaults", || {
+            let project_dir = "/path/to/project";
+            let config_content = r#"
+                [defaults]
+                intro_prompt = "Shared intro"
+                allowed_extensions = ["rs", "toml"]
+                deny_dirs = ["target"]
+
+                ["/path/to/project"]
+                project_name = "Project"
+                output_path = "/path/to/output"
+                deny_dirs = ["target", "vendor"]
+                history = []
+            "#;
+            let config_path = get_config_path().unwrap();
+            fs::write(&config_path, config_content).unwrap();
+
+            // Missing settings are inherited, settings of the project win
+            let mut loaded_config = load_config(project_dir).unwrap();
+            assert_eq!(loaded_config.intro_prompt, "Shared intro");
+            assert_eq!(loaded_config.allowed_extensions, vec!["rs", "toml"]);
+            assert_eq!(loaded_config.deny_dirs, vec!["target", "vendor"]);
+
+            // Inherited settings are not copied into the project section when saving
+            loaded_config.history.push("Goal".to_string());
+            save_config(&loaded_config, project_dir).unwrap();
+            let saved: toml::Table = toml::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+            let project_table = saved[project_dir].as_table().unwrap();
+            assert!(!project_table.contains_key("intro_prompt"));
+            assert!(!project_table.contains_key("allowed_extensions"));
+            assert!(project_table.contains_key("deny_dirs"));
+            assert_eq!(load_config(project_dir).unwrap().history, vec!["Goal"]);
+
+            fs::remove_file(config_path).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_load_project_config_file() {
+        with_test_env("test_load_project_config_file", || {
+            let repo = TempDir::new("project-config");
+            let project_dir = repo.join("app");
+            fs::create_dir_all(repo.join(".git")).unwrap();
+            fs::create_dir_all(&project_dir).unwrap();
+            let project_dir_str = project_dir.to_str().unwrap();
+
+            // The committed file alone is enough
+            fs::write(
+                repo.join(PROJECT_CONFIG_FILE),
+                "project_name = \"App\"\noutput_path = \"\"\nintro_prompt = \"Shared intro\"\nallowed_extensions = [\"rs\"]\ndeny_dirs = [\"target\"]\n",
+            )
+            .unwrap();
+            let config_path = get_config_path().unwrap();
+            let _ = fs::remove_file(&config_path);
+            assert_eq!(load_config(project_dir_str).unwrap().project_name, "App");
+
+            // It wins over the global settings of the project, which win over the defaults
+            let mut global = toml::Table::new();
+            global.insert(DEFAULTS_KEY.to_string(), toml::Value::Table(toml::from_str("language = \"fr\"\ndeny_dirs = [\"dist\"]").unwrap()));
+            global.insert(project_dir_str.to_string(), toml::Value::Table(toml::from_str("intro_prompt = \"Mine\"\ncheck_syntax = true\nhistory = [\"Goal\"]").unwrap()));
+            fs::write(&config_path, toml::to_string(&global).unwrap()).unwrap();
+            let mut loaded_config = load_config(project_dir_str).unwrap();
+            assert_eq!(loaded_config.intro_prompt, "Shared intro");
+            assert_eq!(loaded_config.deny_dirs, vec!["target"]);
+            assert!(loaded_config.check_syntax);
+            assert_eq!(loaded_config.language.as_deref(), Some("fr"));
+            assert_eq!(loaded_config.history, vec!["Goal"]);
+
+            // The settings of the committed file are not copied into the global one
+            loaded_config.history.push("Other goal".to_string());
+            save_config(&loaded_config, project_dir_str).unwrap();
+            let saved: toml::Table = toml::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+            let project_table = saved[project_dir_str].as_table().unwrap();
+            assert!(!project_table.contains_key("project_name"));
+            assert!(!project_table.contains_key("deny_dirs"));
+            assert!(project_table.contains_key("check_syntax"));
+            assert_eq!(load_config(project_dir_str).unwrap().history, vec!["Goal", "Other goal"]);
+
+            fs::remove_file(config_path).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_load_partial_project_config_file() {
+        with_test_env("test_load_partial_project_config_file", || {
+            let repo = TempDir::new("partial-config");
+            let project_dir = repo.join("app");
+            fs::create_dir_all(repo.join(".git")).unwrap();
+            fs::create_dir_all(project_dir.join("src")).unwrap();
+            fs::create_dir_all(project_dir.join("target")).unwrap();
+            fs::write(project_dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+            let project_dir_str = project_dir.to_str().unwrap();
+            let config_path = get_config_path().unwrap();
+            let _ = fs::remove_file(&config_path);
+
+            // The settings missing from the committed file are detected in the project
+            fs::write(repo.join(PROJECT_CONFIG_FILE), "intro_prompt = \"Shared intro\"\ncheck_syntax = true\n").unwrap();
+            let config = load_config(project_dir_str).unwrap();
+            assert_eq!(config.intro_prompt, "Shared intro");
+            assert!(config.check_syntax);
+            assert_eq!(config.project_name, "app");
+            assert_eq!(config.output_path, "");
+            assert_eq!(config.allowed_extensions, vec!["rs"]);
+            assert_eq!(config.deny_dirs, vec!["target"]);
+
+            // The defaults still win over the detected values
+            fs::write(&config_path, "[defaults]\nallowed_extensions = [\"rs\", \"toml\"]\n").unwrap();
+            assert_eq!(load_config(project_dir_str).unwrap().allowed_extensions, vec!["rs", "toml"]);
+
+            fs::remove_file(config_path).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_groups() {
+        with_test_env("test_groups", || {
+            let config_content = r#"
+                [defaults]
+                allowed_extensions = ["rs"]
+
+                ["/path/to/api"]
+                project_name = "Backend"
+                output_path = ""
+                intro_prompt = ""
+                deny_dirs = []
+                history = []
+
+                ["/path/to/web"]
+                project_name = "Frontend"
+                output_path = ""
+                intro_prompt = ""
+                allowed_extensions = ["ts"]
+                deny_dirs = []
+                history = []
+            "#;
+            let config_path = get_config_path().unwrap();
+            fs::write(&config_path, config_content).unwrap();
+
+            // Projects are found by project name or directory name
+            save_group("platform", &["web".to_string(), "Backend".to_string()]).unwrap();
+            assert!(save_group("other", &["shared".to_string()]).is_err());
+            assert_eq!(load_groups().unwrap().into_keys().collect::<Vec<_>>(), vec!["platform"]);
+            let projects = load_group("platform").unwrap();
+            assert_eq!(projects[0].0, PathBuf::from("/path/to/web"));
+            assert_eq!(projects[0].1.allowed_extensions, vec!["ts"]);
+            assert_eq!(projects[1].1.project_name, "Backend");
+            assert_eq!(projects[1].1.allowed_extensions, vec!["rs"]);
+            assert_eq!(load_config("/path/to/api").unwrap().project_name, "Backend");
+
+            fs::remove_file(config_path).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_manage_projects() {
+        with_test_env("test_manage_projects", || {
+            let config_content = r#"
+                [defaults]
+                allowed_extensions = ["rs"]
+
+                ["/path/to/api"]
+                project_name = "Backend"
+                output_path = ""
+                intro_prompt = ""
+                deny_dirs = []
+                history = []
+
+                ["/path/to/old"]
+                project_name = "Gone"
+                output_path = ""
+                intro_prompt = ""
+                deny_dirs = []
+                history = []
+            "#;
+            let config_path = get_config_path().unwrap();
+            fs::write(&config_path, config_content).unwrap();
+
+            let projects = list_projects().unwrap();
+            assert_eq!(projects, vec![(PathBuf::from("/path/to/api"), "Backend".to_string()), (PathBuf::from("/path/to/old"), "Gone".to_string())]);
+
+            rename_project("/path/to/api", "/path/to/backend").unwrap();
+            assert_eq!(load_config("/path/to/backend").unwrap().project_name, "Backend");
+            assert!(rename_project("/path/to/backend", "/path/to/old").is_err());
+            assert!(rename_project("/path/to/api", "/path/to/new").is_err());
+            assert!(rename_project(DEFAULTS_KEY, "/path/to/new").is_err());
+
+            remove_project("/path/to/old").unwrap();
+            assert!(remove_project("/path/to/old").is_err());
+            assert!(remove_project(DEFAULTS_KEY).is_err());
+            assert_eq!(list_projects().unwrap(), vec![(PathBuf::from("/path/to/backend"), "Backend".to_string())]);
+
+            fs::remove_file(config_path).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_set_setting() {
+        with_test_env("test_set_setting", || {
+            let project_dir = "/path/to/project";
+            let config_content = r#"
+                [defaults]
+                allowed_extensions = ["rs"]
+
+                ["/path/to/project"]
+                project_name = "Project"
+                output_path = ""
+                intro_prompt = ""
+                deny_dirs = []
+                history = []
+            "#;
+            let config_path = get_config_path().unwrap();
+            fs::write(&config_path, config_content).unwrap();
+
+            // Values are read with the type of the setting
+            set_setting(project_dir, "allowed_extensions", "rs, toml").unwrap();
+            set_setting(project_dir, "check_syntax", "true").unwrap();
+            set_setting(project_dir, "max_tokens", "100000").unwrap();
+            set_setting(project_dir, "language", "fr").unwrap();
+            let config = load_config(project_dir).unwrap();
+            assert_eq!(config.allowed_extensions, vec!["rs", "toml"]);
+            assert!(config.check_syntax);
+            assert_eq!(config.max_tokens, Some(100000));
+            assert_eq!(config.language.as_deref(), Some("fr"));
+            assert!(set_setting(project_dir, "check_syntax", "maybe").is_err());
+            assert!(set_setting(project_dir, "no_such_setting", "1").is_err());
+            assert!(set_setting("/path/to/other", "language", "fr").is_err());
+
+            // Unset settings fall back to the defaults, required ones stay
+            unset_setting(project_dir, "allowed_extensions").unwrap();
+            assert_eq!(load_config(project_dir).unwrap().allowed_extensions, vec!["rs"]);
+            assert!(unset_setting(project_dir, "project_name").is_err());
+            assert_eq!(project_section(project_dir).unwrap()["project_name"].as_str(), Some("Project"));
+
+            fs::remove_file(config_path).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_save_snippets() {
+        with_test_env("test_save_snippets", || {
+            let current_dir = "/path/to/app";
+            let config_path = get_config_path().unwrap();
+            let _ = fs::remove_file(&config_path);
+            let mut config = Config {
+                project_name: "app".to_string(),
+                ..Default::default()
+            };
+            save_config(&config, current_dir).unwrap();
+            assert!(!fs::read_to_string(&config_path).unwrap().contains("snippets"));
+
+            // Multi-line snippets are stored as is and loaded back
+            config.snippets.insert("patch-format".to_string(), "Answer with a unified diff.\nKeep the hunks small.".to_string());
+            config.snippets.insert("style".to_string(), "Follow the style of the project.".to_string());
+            save_config(&config, current_dir).unwrap();
+            assert_eq!(load_config(current_dir).unwrap().snippets, config.snippets);
+
+            fs::remove_file(config_path).unwrap();
+        });
+    }
+
     #[test]
     fn test_load_multiple_configs() {
         with_test_env("test_load_multiple_configs", || {
@@ -0,0 +1,129 @@
+// src/diagnose.rs
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+/// Number of directory entries inspected, to keep the diagnostic fast on huge trees.
+const ENTRY_LIMIT: usize = 50_000;
+
+/// Number of extensions listed in the diagnostic.
+const TOP_EXTENSIONS: usize = 5;
+
+/// Files found for one extension.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ExtensionCount {
+    /// Files outside the denied directories.
+    visible: usize,
+    /// Files under a denied directory.
+    denied: usize,
+}
+
+/// Explains why no file matched the filters: the most common extensions present under the
+/// roots and why their files were left out, then the deny patterns that skipped files.
+///
+/// # Arguments
+/// * `config` - The configuration the prompt was built with.
+/// * `roots` - The directories walked for the prompt.
+/// * `project_dir` - The project directory, which deny patterns are relative to.
+pub fn explain_empty(config: &Config, roots: &[&Path], project_dir: &Path) -> String {
+    let mut extensions: BTreeMap<String, ExtensionCount> = BTreeMap::new();
+    let mut denied_by: BTreeMap<String, usize> = BTreeMap::new();
+    let mut pending: Vec<(PathBuf, Option<String>)> = roots.iter().map(|root| (root.to_path_buf(), None)).collect();
+    let mut seen = 0;
+    while let Some((dir, denying_pattern)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            seen += 1;
+            if seen > ENTRY_LIMIT {
+                break;
+            }
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|file_type| file_type.is_symlink()) {
+                continue;
+            }
+            if path.is_dir() {
+                let relative_path = path.strip_prefix(project_dir).unwrap_or(&path);
+                let denying_pattern = denying_pattern.clone().or_else(|| config.denying_pattern(relative_path).or_else(|| config.build_output(&path, relative_path)).map(str::to_string));
+                pending.push((path, denying_pattern));
+            } else if let Some(extension) = path.extension() {
+                let count = extensions.entry(extension.to_string_lossy().into_owned()).or_default();
+                match &denying_pattern {
+                    Some(pattern) => {
+                        count.denied += 1;
+                        *denied_by.entry(pattern.clone()).or_default() += 1;
+                    }
+                    None => count.visible += 1,
+                }
+            }
+        }
+    }
+
+    let mut report = String::from("No files match the filters, the prompt would only contain the introduction and the goal.\n");
+    if extensions.is_empty() {
+        report.push_str("The project roots contain no files with an extension.\n");
+        return report;
+    }
+    let mut extensions: Vec<(String, ExtensionCount)> = extensions.into_iter().collect();
+    extensions.sort_by(|a, b| (b.1.visible + b.1.denied).cmp(&(a.1.visible + a.1.denied)).then_with(|| a.0.cmp(&b.0)));
+
+    report.push_str("Most common extensions in the project:\n");
+    for (extension, count) in extensions.iter().take(TOP_EXTENSIONS) {
+        let reason = if !config.is_allowed_extension(extension) {
+            "not in allowed_extensions"
+        } else {
+            "all under denied directories"
+        };
+        let _ = writeln!(report, "  {}: {} ({})", extension, files(count.visible + count.denied), reason);
+    }
+    if !denied_by.is_empty() {
+        report.push_str("Files skipped by deny_dirs:\n");
+        for (pattern, count) in &denied_by {
+            let _ = writeln!(report, "  {}: {}", pattern, files(*count));
+        }
+    }
+    let _ = writeln!(report, "Allowed extensions: {}", config.allowed_extensions.join(", "));
+    if let Some((extension, _)) = extensions.iter().find(|(extension, count)| count.visible > 0 && !config.is_allowed_extension(extension)) {
+        let _ = writeln!(report, "Try `--also-allow {}`, or adjust allowed_extensions and deny_dirs in the configuration.", extension);
+    }
+    report
+}
+
+fn files(count: usize) -> String {
+    if count == 1 {
+        "1 file".to_string()
+    } else {
+        format!("{} files", count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_explain_empty() {
+        let project_dir = TempDir::new("diagnose");
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::create_dir_all(project_dir.join("gen")).unwrap();
+        fs::write(project_dir.join("src/main.py"), "").unwrap();
+        fs::write(project_dir.join("src/util.py"), "").unwrap();
+        fs::write(project_dir.join("gen/api.rs"), "").unwrap();
+        let config = Config {
+            allowed_extensions: vec!["rs".to_string()],
+            deny_dirs: vec!["gen".to_string()],
+            ..Default::default()
+        };
+
+        let report = explain_empty(&config, &[&*project_dir], &project_dir);
+        assert!(report.contains("  py: 2 files (not in allowed_extensions)\n  rs: 1 file (all under denied directories)\n"));
+        assert!(report.contains("Files skipped by deny_dirs:\n  gen: 1 file\n"));
+        assert!(report.contains("Try `--also-allow py`"));
+    }
+}
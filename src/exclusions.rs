@@ -0,0 +1,113 @@
+// src/exclusions.rs
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// A filter rule that can leave a file out of a prompt.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rule {
+    /// A `deny_dirs` pattern, or the default output directory.
+    DenyDir(String),
+    /// A built-in build output directory, with `deny_build_outputs`.
+    BuildOutput(String),
+    /// The extension is not in `allowed_extensions`, nor the name in `allowed_filenames`.
+    Extension,
+    /// Ignored by git, through `.gitignore`, `.git/info/exclude` or the global excludes file.
+    Gitignore,
+    /// A symbolic link resolving outside the project directory.
+    OutsideRoot,
+    /// The file is not listed in the `--files-from-changeset` file.
+    Changeset,
+    /// The output directory, or a prompt written by a previous run.
+    Output,
+    /// The file opts out with a `prompt-gen:ignore` marker in its first lines.
+    OptOut,
+    /// Left out to fit the prompt in `max_tokens`, with `over_budget = "trim"`.
+    Budget,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rule::DenyDir(pattern) => write!(f, "deny_dirs \"{}\"", pattern),
+            Rule::BuildOutput(name) => write!(f, "build output \"{}\"", name),
+            Rule::Extension => write!(f, "allowed_extensions"),
+            Rule::Gitignore => write!(f, "gitignore"),
+            Rule::OutsideRoot => write!(f, "outside the project directory"),
+            Rule::Changeset => write!(f, "not in the changeset"),
+            Rule::Output => write!(f, "prompt-gen output"),
+            Rule::OptOut => write!(f, "prompt-gen:ignore marker"),
+            Rule::Budget => write!(f, "max_tokens"),
+        }
+    }
+}
+
+/// Number of files excluded by each rule during a walk, reported with `--explain`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Exclusions {
+    counts: BTreeMap<Rule, usize>,
+}
+
+impl Exclusions {
+    /// Records `files` files excluded by `rule`.
+    pub fn record(&mut self, rule: Rule, files: usize) {
+        *self.counts.entry(rule).or_default() += files;
+    }
+
+    /// Records the files under `dir`, skipped by `rule` as a whole. Symbolic links are not followed.
+    pub fn record_dir(&mut self, rule: Rule, dir: &Path) {
+        self.record(rule, count_files(dir));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+}
+
+impl fmt::Display for Exclusions {
+    /// One line per rule, the rules excluding the most files first.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut counts: Vec<(&Rule, &usize)> = self.counts.iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (rule, files) in counts {
+            writeln!(f, "  {}: {} {}", rule, files, if *files == 1 { "file" } else { "files" })?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts the files under `dir`, recursively.
+fn count_files(dir: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => count_files(&entry.path()),
+            Ok(file_type) if file_type.is_symlink() => 0,
+            _ => 1,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_exclusions() {
+        let dir = TempDir::new("exclusions");
+        fs::create_dir_all(dir.join("debug/deps")).unwrap();
+        fs::write(dir.join("debug/app"), "").unwrap();
+        fs::write(dir.join("debug/deps/a.rlib"), "").unwrap();
+
+        let mut exclusions = Exclusions::default();
+        exclusions.record(Rule::Extension, 1);
+        exclusions.record_dir(Rule::DenyDir("target".to_string()), &dir);
+        assert_eq!(exclusions.to_string(), "  deny_dirs \"target\": 2 files\n  allowed_extensions: 1 file\n");
+    }
+}
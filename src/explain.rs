@@ -0,0 +1,187 @@
+// src/explain.rs
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, OverBudget};
+use crate::exclusions::Rule;
+use crate::gitignore::IgnoreRules;
+use crate::pipeline::{filter, BuildOutputs, DenyDirs, Entry, Extensions, Filter, OptOut, Output, OutsideRoot};
+use crate::{budget, build_context, containment, project_roots, BuildOptions};
+
+/// Why a path is or is not part of the prompts of a project, as reported by `prompt-gen explain`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Fate {
+    /// The file content is included.
+    Included {
+        /// Whether it is reduced to a skeleton by `reference_globs`.
+        reference: bool,
+    },
+    /// The directory is walked.
+    Walked,
+    /// The path is left out by `rule`, applied to `at` (the path itself or a parent directory).
+    Excluded { rule: Rule, at: PathBuf },
+    /// The file would be included, but the prompt exceeds `max_tokens` and is refused.
+    Refused { prompt_tokens: usize, max_tokens: usize },
+    /// The path is not under any project root.
+    NotInRoots,
+    /// The path does not exist.
+    Missing,
+}
+
+impl fmt::Display for Fate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fate::Included { reference: false } => write!(f, "included"),
+            Fate::Included { reference: true } => write!(f, "included as a skeleton, labeled reference only (reference_globs)"),
+            Fate::Walked => write!(f, "walked, its files are filtered by extension"),
+            Fate::Excluded { rule, at } => write!(f, "excluded by {} (at {})", rule, at.display()),
+            Fate::Refused { prompt_tokens, max_tokens } => write!(
+                f,
+                "included, but the prompt takes {} tokens, over the max_tokens budget of {}, and is refused",
+                prompt_tokens, max_tokens
+            ),
+            Fate::NotInRoots => write!(f, "excluded, it is not under any project root"),
+            Fate::Missing => write!(f, "does not exist"),
+        }
+    }
+}
+
+/// Decides the fate of `path` with the filters the walk applies, in the same order, to every
+/// directory from the root down and to the path itself, then with the `max_tokens` budget.
+///
+/// # Arguments
+/// * `path` - The file or directory, relative to `project_dir` or absolute.
+pub fn explain(config: &Config, project_dir: &Path, path: &Path) -> io::Result<Fate> {
+    let path = project_dir.join(path);
+    if !path.exists() {
+        return Ok(Fate::Missing);
+    }
+    let containment = containment(project_dir);
+    let canonical = path.canonicalize()?;
+    let Some(root) = project_roots(config, project_dir, Some(&containment), &mut Vec::new())
+        .into_iter()
+        .filter_map(|root| root.path.canonicalize().ok())
+        .find(|root| canonical.starts_with(root))
+    else {
+        return Ok(Fate::NotInRoots);
+    };
+
+    // Apply the filters of the walk to each directory from the root down, then to the path itself
+    let mut ignore = config.respects_gitignore().then(|| IgnoreRules::for_dir(&root)).flatten();
+    let output_dir = config.output_dir(project_dir).canonicalize().ok();
+    let mut current = root.clone();
+    let components: Vec<_> = canonical.strip_prefix(&root).unwrap_or(Path::new("")).components().collect();
+    for (i, component) in components.iter().enumerate() {
+        current.push(component);
+        let lexical = path_below(project_dir, &path, components.len() - i - 1);
+        let is_dir = current.is_dir();
+        let entry = Entry {
+            path: &current,
+            project_path: current.strip_prefix(&containment).or_else(|_| current.strip_prefix(&root)).unwrap_or(&current),
+            is_dir,
+            is_symlink: lexical.symlink_metadata().is_ok_and(|metadata| metadata.is_symlink()),
+        };
+        let filters: [&dyn Filter; 7] = [
+            &OutsideRoot(Some(&containment)),
+            &ignore,
+            &DenyDirs(config),
+            &BuildOutputs(config),
+            &Output {
+                config,
+                dir: output_dir.as_deref(),
+            },
+            &Extensions(config),
+            &OptOut,
+        ];
+        if let Some(rule) = filter(&filters, &entry) {
+            return Ok(excluded(rule, &current, &containment));
+        }
+        if let (Some(ignore), true) = (&mut ignore, is_dir) {
+            ignore.enter(&current);
+        }
+    }
+    if canonical.is_dir() {
+        return Ok(Fate::Walked);
+    }
+
+    if let Some(max_tokens) = config.max_tokens {
+        let context = build_context(config, project_dir, &BuildOptions::default())?;
+        let prompt_tokens = config.tokenizer.count(&String::from_utf8_lossy(&context.content));
+        if prompt_tokens > max_tokens {
+            if config.over_budget == OverBudget::Refuse {
+                return Ok(Fate::Refused { prompt_tokens, max_tokens });
+            }
+            let sizes = budget::file_tokens(config, project_dir, &context.files)?;
+            let dropped = budget::files_to_drop(&sizes, prompt_tokens - max_tokens);
+            if dropped.iter().any(|size| size.file.source.canonicalize().is_ok_and(|source| source == canonical)) {
+                return Ok(excluded(Rule::Budget, &canonical, &containment));
+            }
+        }
+    }
+    let reference = canonical.strip_prefix(&containment).is_ok_and(|project_path| config.is_reference(project_path));
+    Ok(Fate::Included { reference })
+}
+
+/// Returns `path` without its last `levels` components.
+fn path_below(project_dir: &Path, path: &Path, levels: usize) -> PathBuf {
+    let mut below = path.to_path_buf();
+    for _ in 0..levels {
+        if !below.pop() {
+            return project_dir.to_path_buf();
+        }
+    }
+    below
+}
+
+/// An exclusion at `at`, shown relative to the project directory when it is inside.
+fn excluded(rule: Rule, at: &Path, project_dir: &Path) -> Fate {
+    Fate::Excluded {
+        rule,
+        at: at.strip_prefix(project_dir).unwrap_or(at).to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_explain() {
+        let project_dir = TempDir::new("explain");
+        fs::create_dir_all(project_dir.join("src/generated")).unwrap();
+        fs::create_dir_all(project_dir.join(".git")).unwrap();
+        fs::write(project_dir.join(".gitignore"), "*.bak.rs\n").unwrap();
+        fs::write(project_dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(project_dir.join("src/old.bak.rs"), "fn main() {}\n").unwrap();
+        fs::write(project_dir.join("src/generated/api.rs"), "fn api() {}\n").unwrap();
+        fs::write(project_dir.join("src/keys.rs"), "#![allow(dead_code)]\n// prompt-gen:ignore\nconst KEY: &str = \"\";\n").unwrap();
+        fs::write(project_dir.join("README.md"), "# Readme\n").unwrap();
+        fs::write(project_dir.join("app_20240101.txt"), "").unwrap();
+        fs::create_dir_all(project_dir.join("prompts")).unwrap();
+        let project_dir = project_dir.canonicalize().unwrap();
+        let config = Config {
+            project_name: "app".to_string(),
+            allowed_extensions: vec!["rs".to_string(), "txt".to_string()],
+            output_path: project_dir.join("prompts").display().to_string(),
+            deny_dirs: vec!["generated".to_string()],
+            reference_globs: vec!["main.rs".to_string()],
+            ..Default::default()
+        };
+
+        let fate = |path: &str| explain(&config, &project_dir, Path::new(path)).unwrap();
+        assert_eq!(fate("src/main.rs"), Fate::Included { reference: true });
+        assert_eq!(fate("src"), Fate::Walked);
+        assert_eq!(fate("src/generated/api.rs"), Fate::Excluded { rule: Rule::DenyDir("generated".to_string()), at: PathBuf::from("src/generated") });
+        assert_eq!(fate("src/old.bak.rs"), Fate::Excluded { rule: Rule::Gitignore, at: PathBuf::from("src/old.bak.rs") });
+        assert_eq!(fate("src/keys.rs"), Fate::Excluded { rule: Rule::OptOut, at: PathBuf::from("src/keys.rs") });
+        assert_eq!(fate("README.md"), Fate::Excluded { rule: Rule::Extension, at: PathBuf::from("README.md") });
+        assert_eq!(fate("prompts"), Fate::Excluded { rule: Rule::Output, at: PathBuf::from("prompts") });
+        assert_eq!(fate("app_20240101.txt"), Fate::Excluded { rule: Rule::Output, at: PathBuf::from("app_20240101.txt") });
+        assert_eq!(fate("missing.rs"), Fate::Missing);
+    }
+}
@@ -0,0 +1,67 @@
+// src/followup.rs
+
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::atomic::AtomicFile;
+use crate::config::Config;
+use crate::manifest::{self, Manifest};
+use crate::write_source_file;
+
+/// Builds a follow-up prompt from the latest prompt manifest of the project.
+///
+/// Only the files of the previous prompt that `response` (the model's answer, read from
+/// `response_path` or stdin) modified or mentioned are included, with their current content,
+/// followed by the previous goal and the new instruction.
+///
+/// # Returns
+/// The path of the generated prompt file.
+pub fn run(config: &Config, project_dir: &Path, instruction: &str, response_path: Option<&Path>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let previous = manifest::load_latest_manifest(&config.output_dir(project_dir), &config.project_name)?;
+    let response = match response_path {
+        Some(path) => fs::read_to_string(path)?,
+        None if io::stdin().is_terminal() => {
+            return Err("Pass the previous response with --response FILE or pipe it to stdin".into());
+        }
+        None => {
+            let mut response = String::new();
+            io::stdin().read_to_string(&mut response)?;
+            response
+        }
+    };
+
+    let mentioned = manifest::mentioned_files(&previous, &response);
+    if mentioned.is_empty() {
+        eprintln!("Warning: the response mentions none of the files of the previous prompt.");
+    }
+
+    let current_date = chrono::Local::now().format("%Y%m%d").to_string();
+    let prompt_path = config.create_output_dir(project_dir)?.join(format!("{}_{}_followup.{}", config.project_name, current_date, config.output_format.extension()));
+    let mut prompt_file = AtomicFile::create(&prompt_path)?;
+    writeln!(prompt_file, "{}", config.intro_prompt)?;
+    for file in &mentioned {
+        write_source_file(&mut prompt_file, config, project_dir, &file.source, &file.path)?;
+    }
+    let strings = config.strings();
+    writeln!(prompt_file, "{}: {}", strings.previous_goal, previous.goal)?;
+    writeln!(prompt_file, "{}: {}", strings.follow_up, instruction)?;
+    if config.answer_with_diff {
+        writeln!(prompt_file, "{}", strings.diff_instruction)?;
+    }
+    if config.answer_in_language {
+        writeln!(prompt_file, "{}", strings.answer_instruction)?;
+    }
+    if let Some(outro_prompt) = &config.outro_prompt {
+        writeln!(prompt_file, "{}", outro_prompt.trim_end())?;
+    }
+    prompt_file.commit()?;
+
+    // Chained follow-ups start from this prompt
+    let manifest = Manifest {
+        goal: instruction.to_string(),
+        files: mentioned.into_iter().cloned().collect(),
+    };
+    manifest::save_manifest(&manifest, &prompt_path)?;
+    Ok(prompt_path)
+}
@@ -0,0 +1,388 @@
+// src/generate.rs
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::atomic::AtomicFile;
+use crate::config::{Config, OverBudget};
+use crate::manifest::{self, Manifest};
+use crate::{budget, build_context, containment, diagnose, project_roots, prompt_filename, prompt_path, template, tokens, write_prompt, BuildOptions, Context};
+
+/// The steps shared by every way of generating a prompt: the command line, [`generate`],
+/// [`PromptBuilder`](crate::PromptBuilder), `rpc` and `watch`.
+pub(crate) struct Generation<'a> {
+    /// The configuration, whose introductory and closing prompts may have template slots.
+    pub config: Config,
+    /// The directory the files are read from: the project, or a snapshot of one of its revisions.
+    pub source_dir: &'a Path,
+    pub options: BuildOptions,
+    /// Content of the `{{attachment "name"}}` slots, given with `--attach`.
+    pub attachments: BTreeMap<String, String>,
+}
+
+/// A prompt ready to be written, returned by [`Generation::run`].
+pub(crate) struct Generated {
+    /// The configuration with the slots of its introductory and closing prompts filled.
+    pub config: Config,
+    pub context: Context,
+    /// The goal resolved by `empty_goal`, as recorded in the manifest and the history.
+    pub goal: String,
+    /// The goal with its slots filled, as written in the prompt.
+    pub rendered_goal: String,
+}
+
+impl<'a> Generation<'a> {
+    /// A generation of the project files in `source_dir` with `config`, without attachments.
+    pub fn new(config: Config, source_dir: &'a Path, options: BuildOptions) -> Self {
+        Generation {
+            config,
+            source_dir,
+            options,
+            attachments: BTreeMap::new(),
+        }
+    }
+
+    /// Runs the steps of a prompt generation:
+    /// - the slots of the introductory and closing prompts are filled;
+    /// - the context is built, failing with an explanation when no file is included;
+    /// - files are left out, or generation fails, when the prompt exceeds `max_tokens`, counting
+    ///   `goal_hint` when the goal is known up front;
+    /// - `ask_goal` returns the goal, which is resolved by `empty_goal` and has its slots filled.
+    ///
+    /// Warnings about redacted secrets, files left out and unused attachments go to `status`.
+    pub fn run(
+        self,
+        goal_hint: Option<&str>,
+        status: &mut dyn Write,
+        ask_goal: impl FnOnce(&Config, &Context, &mut dyn Write) -> io::Result<String>,
+    ) -> io::Result<Generated> {
+        let mut used_attachments = BTreeSet::new();
+        let (config, context) = self.build(goal_hint, &mut used_attachments, &mut *status)?;
+
+        let goal = ask_goal(&config, &context, &mut *status)?;
+        let goal = config.resolve_goal(&goal).map_err(invalid_input)?;
+        let rendered_goal = template::render(&goal, &self.attachments, &config.snippets, &mut used_attachments).map_err(invalid_input)?;
+        for name in self.attachments.keys().filter(|name| !used_attachments.contains(*name)) {
+            writeln!(status, "Warning: attachment \"{}\" is not used by any {{{{attachment \"{}\"}}}} slot.", name, name)?;
+        }
+        Ok(Generated {
+            config,
+            context,
+            goal,
+            rendered_goal,
+        })
+    }
+
+    /// Runs the steps of [`Generation::run`] for a goal known up front.
+    pub fn run_with_goal(self, goal: &str, status: &mut dyn Write) -> io::Result<Generated> {
+        self.run(Some(goal), status, |_, _, _| Ok(goal.to_string()))
+    }
+
+    /// Runs the steps of [`Generation::run`] that precede the goal, for dry runs. Returns the
+    /// configuration with its prompts filled and the context.
+    pub fn run_without_goal(self, status: &mut dyn Write) -> io::Result<(Config, Context)> {
+        self.build(None, &mut BTreeSet::new(), status)
+    }
+
+    /// Fills the slots of the introductory and closing prompts, then builds the context within
+    /// the budget and reports its redacted secrets.
+    fn build(&self, goal_hint: Option<&str>, used_attachments: &mut BTreeSet<String>, status: &mut dyn Write) -> io::Result<(Config, Context)> {
+        let mut config = self.config.clone();
+        config.intro_prompt = template::render(&config.intro_prompt, &self.attachments, &config.snippets, used_attachments).map_err(invalid_input)?;
+        if let Some(outro_prompt) = &config.outro_prompt {
+            config.outro_prompt = Some(template::render(outro_prompt, &self.attachments, &config.snippets, used_attachments).map_err(invalid_input)?);
+        }
+
+        let goal_tokens = goal_hint.map_or(0, |goal| config.tokenizer.count(goal));
+        let context = build_within_budget(&config, self.source_dir, &self.options, goal_tokens, &mut *status);
+        // Save the counts of the files even when the prompt is over budget, for the next attempt
+        tokens::save_counts(&mut *status)?;
+        let context = context?;
+        for redaction in &context.redactions {
+            writeln!(status, "Warning: {}.", redaction)?;
+        }
+        for warning in &context.warnings {
+            writeln!(status, "Warning: {}.", warning)?;
+        }
+        Ok((config, context))
+    }
+}
+
+fn invalid_input(e: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e)
+}
+
+/// Builds the context, leaving out the largest files when it exceeds `max_tokens` with
+/// `over_budget = "trim"`, and failing with the largest files otherwise.
+fn build_within_budget(config: &Config, source_dir: &Path, options: &BuildOptions, goal_tokens: usize, status: &mut dyn Write) -> io::Result<Context> {
+    let context = build_context(config, source_dir, options)?;
+    // An empty prompt is never what was wanted, explain what the filters left out instead
+    if context.files.is_empty() && options.changeset.is_none() {
+        let containment = (!options.allow_outside_root).then(|| containment(source_dir));
+        // The roots skipped are already among the warnings of the context
+        let roots = project_roots(config, source_dir, containment.as_deref(), &mut Vec::new());
+        let root_paths: Vec<&Path> = roots.iter().map(|root| root.path.as_path()).collect();
+        return Err(io::Error::other(diagnose::explain_empty(config, &root_paths, source_dir).trim_end().to_string()));
+    }
+    let Some(max_tokens) = config.max_tokens else {
+        return Ok(context);
+    };
+    let prompt_tokens = config.tokenizer.count(&String::from_utf8_lossy(&context.content)) + goal_tokens;
+    if prompt_tokens <= max_tokens {
+        return Ok(context);
+    }
+    let sizes = budget::file_tokens(config, source_dir, &context.files)?;
+    if config.over_budget == OverBudget::Refuse {
+        return Err(io::Error::other(budget::report(prompt_tokens, max_tokens, &sizes).trim_end().to_string()));
+    }
+    let dropped = budget::files_to_drop(&sizes, prompt_tokens - max_tokens);
+    for size in dropped {
+        writeln!(status, "Warning: left out {} ({} tokens) to fit the max_tokens budget of {}.", size.file.path, size.tokens, max_tokens)?;
+    }
+    let kept: HashSet<PathBuf> = context
+        .files
+        .iter()
+        .filter(|file| !dropped.iter().any(|size| size.file == *file))
+        .filter_map(|file| file.source.strip_prefix(source_dir).ok().map(Path::to_path_buf))
+        .collect();
+    let trimmed_options = BuildOptions {
+        changeset: Some(kept),
+        ..options.clone()
+    };
+    let context = build_context(config, source_dir, &trimmed_options)?;
+    let prompt_tokens = config.tokenizer.count(&String::from_utf8_lossy(&context.content)) + goal_tokens;
+    if prompt_tokens > max_tokens {
+        return Err(io::Error::other(format!(
+            "the prompt still takes {} tokens without its files, over the max_tokens budget of {}.",
+            prompt_tokens, max_tokens
+        )));
+    }
+    Ok(context)
+}
+
+/// What to generate with [`generate`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct GenerateOptions {
+    /// The project directory.
+    pub project_dir: PathBuf,
+    /// The goal ending the prompt.
+    pub goal: String,
+    /// The configuration to use. When `None`, the configuration stored for `project_dir` in
+    /// the global configuration file is loaded.
+    pub config: Option<Config>,
+    /// Where to write the prompt: a file, or a directory receiving the usual
+    /// `project_name_YYYYMMDD.txt` file. Defaults to the configured output directory.
+    pub output: Option<PathBuf>,
+    /// Stop walking and cleaning files after this duration, writing a partial prompt.
+    pub timeout: Option<Duration>,
+}
+
+impl GenerateOptions {
+    /// Options generating a prompt for `goal` with the stored configuration of `project_dir`.
+    pub fn new(project_dir: impl Into<PathBuf>, goal: impl Into<String>) -> Self {
+        GenerateOptions {
+            project_dir: project_dir.into(),
+            goal: goal.into(),
+            config: None,
+            output: None,
+            timeout: None,
+        }
+    }
+
+    /// Uses `config` instead of the stored configuration.
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Writes the prompt to `output`, a file or a directory.
+    pub fn with_output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    /// Limits the time spent walking and cleaning files.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+/// The files written by [`generate`] and statistics about the prompt.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PromptArtifacts {
+    /// The prompt file.
+    pub prompt_path: PathBuf,
+    /// The manifest listing the goal and the included files, next to the prompt.
+    pub manifest_path: PathBuf,
+    /// Paths of the included files, as shown in the prompt.
+    pub files: Vec<String>,
+    /// Estimated number of tokens of the prompt.
+    pub tokens: usize,
+    /// Whether generation was cut short by the timeout.
+    pub partial: bool,
+    /// Time spent generating the prompt.
+    pub duration: Duration,
+    /// Warnings about the prompt, such as redacted secrets or files left out to fit `max_tokens`.
+    pub warnings: Vec<String>,
+}
+
+/// Generates a prompt file and its manifest without any interaction, for use from `build.rs`
+/// or xtask binaries. Unlike the command line, the goal is not added to the project history.
+///
+/// The prompt goes through the same steps as on the command line: snippets are inserted in the
+/// introductory and closing prompts and in the goal, and `max_tokens` is enforced.
+pub fn generate(options: &GenerateOptions) -> Result<PromptArtifacts, Box<dyn std::error::Error>> {
+    let started = Instant::now();
+    let project_dir = options.project_dir.canonicalize()?;
+    let config = match &options.config {
+        Some(config) => config.clone(),
+        None => Config::load(&project_dir)?,
+    };
+
+    let build_options = BuildOptions {
+        timeout: options.timeout,
+        ..Default::default()
+    };
+    let mut warnings = Vec::new();
+    let generated = Generation::new(config, &project_dir, build_options).run_with_goal(&options.goal, &mut warnings)?;
+    let (config, context) = (&generated.config, &generated.context);
+    let prompt_path = match &options.output {
+        Some(output) if output.is_dir() => output.join(prompt_filename(config)),
+        Some(output) => output.clone(),
+        None => prompt_path(config, &project_dir)?,
+    };
+    let mut prompt_file = AtomicFile::create(&prompt_path)?;
+    write_prompt(&mut prompt_file, context, &generated.rendered_goal, options.timeout)?;
+    prompt_file.commit()?;
+
+    let manifest = Manifest {
+        goal: generated.goal.clone(),
+        files: context.files.clone(),
+    };
+    manifest::save_manifest(&manifest, &prompt_path)?;
+
+    let tokens = config.tokenizer.count(&String::from_utf8_lossy(&context.content)) + config.tokenizer.count(&generated.rendered_goal);
+    tokens::save_counts(&mut warnings)?;
+    Ok(PromptArtifacts {
+        manifest_path: manifest::manifest_path(&prompt_path),
+        prompt_path,
+        files: context.files.iter().map(|file| file.path.clone()).collect(),
+        tokens,
+        partial: context.timed_out,
+        duration: started.elapsed(),
+        warnings: String::from_utf8_lossy(&warnings).lines().map(str::to_string).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_generate() {
+        let project_dir = TempDir::new("generate");
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::write(project_dir.join("src/lib.rs"), "pub fn answer() -> u32 { 42 }\n").unwrap();
+        let config = Config {
+            project_name: "lib".to_string(),
+            intro_prompt: "Intro".to_string(),
+            allowed_extensions: vec!["rs".to_string()],
+            ..Default::default()
+        };
+
+        let output = project_dir.join("prompt.txt");
+        let options = GenerateOptions::new(&project_dir, "Document the API").with_config(config.clone()).with_output(&output);
+        let artifacts = generate(&options).unwrap();
+        assert_eq!(artifacts.prompt_path, output);
+        assert_eq!(artifacts.files, vec!["src/lib.rs"]);
+        assert!(!artifacts.partial);
+        assert!(artifacts.manifest_path.is_file());
+        assert!(fs::read_to_string(&output).unwrap().ends_with("Specific Goal: Document the API\n"));
+
+        // Walking more entries than allowed fails with guidance
+        let capped = Config { max_entries: Some(1), ..config };
+        let options = GenerateOptions::new(&project_dir, "Document the API").with_config(capped).with_output(project_dir.join("capped.txt"));
+        let error = generate(&options).unwrap_err().to_string();
+        assert!(error.contains("deny_dirs"), "{}", error);
+        assert!(!project_dir.join("capped.txt").exists());
+    }
+
+    #[test]
+    fn test_generate_templates_and_budget() {
+        let dir = TempDir::new("generate-pipeline");
+        dir.write("src/lib.rs", "pub fn answer() -> u32 { 42 }\n");
+        dir.write("src/data.rs", &format!("pub const DATA: &str = \"{}\";\n", "data ".repeat(400)));
+        let config = Config {
+            project_name: "lib".to_string(),
+            intro_prompt: "Intro. {{snippet \"style\"}}".to_string(),
+            outro_prompt: Some("{{snippet \"style\"}}".to_string()),
+            allowed_extensions: vec!["rs".to_string()],
+            snippets: [("style".to_string(), "Follow the style of the project.".to_string())].into(),
+            ..Default::default()
+        };
+        let generate_to = |config: &Config, output: &str, goal: &str| {
+            let options = GenerateOptions::new(&*dir, goal).with_config(config.clone()).with_output(dir.join(output));
+            generate(&options)
+        };
+
+        // Snippets are inserted in the introductory and closing prompts and in the goal
+        let artifacts = generate_to(&config, "prompt.txt", "Review {{snippet \"style\"}}").unwrap();
+        let prompt = fs::read_to_string(&artifacts.prompt_path).unwrap();
+        assert!(prompt.starts_with("Intro. Follow the style of the project.\n"));
+        assert!(prompt.contains("Specific Goal: Review Follow the style of the project.\n"));
+        assert!(prompt.ends_with("\nFollow the style of the project.\n"));
+        assert!(!prompt.contains("{{snippet"));
+        assert!(artifacts.warnings.is_empty());
+        let missing = Config {
+            snippets: Default::default(),
+            ..config.clone()
+        };
+        let error = generate_to(&missing, "missing.txt", "Review").unwrap_err().to_string();
+        assert!(error.contains("the template requires the snippet \"style\""), "{}", error);
+
+        // Over max_tokens, the largest files are left out or generation fails
+        let trimmed = Config {
+            max_tokens: Some(200),
+            over_budget: OverBudget::Trim,
+            ..config.clone()
+        };
+        let artifacts = generate_to(&trimmed, "trimmed.txt", "Review").unwrap();
+        assert_eq!(artifacts.files, vec!["src/lib.rs"]);
+        assert!(artifacts.tokens <= 200);
+        assert_eq!(artifacts.warnings.len(), 1);
+        assert!(artifacts.warnings[0].starts_with("Warning: left out src/data.rs ("), "{:?}", artifacts.warnings);
+        let refused = Config {
+            over_budget: OverBudget::Refuse,
+            ..trimmed
+        };
+        let error = generate_to(&refused, "refused.txt", "Review").unwrap_err().to_string();
+        assert!(error.contains("over the max_tokens budget of 200"), "{}", error);
+        assert!(!dir.join("refused.txt").exists());
+    }
+
+    #[test]
+    fn test_generate_clean_warnings() {
+        let dir = TempDir::new("generate-clean-warnings");
+        let logo = dir.write(
+            "src/logo.rs",
+            "pub const LOGO: &str = \"iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg+/Zx9Qw3Lm\";\n",
+        );
+        let config = Config {
+            allowed_extensions: vec!["rs".to_string()],
+            ..Default::default()
+        };
+
+        // Warnings of the clean stage are reported with the others, not printed
+        let options = GenerateOptions::new(&*dir, "Review").with_config(config).with_output(dir.join("prompt.txt"));
+        let artifacts = generate(&options).unwrap();
+        assert_eq!(artifacts.warnings, vec![format!("Warning: omitted 1 high-entropy data blob(s) from {}.", logo.display())]);
+    }
+}
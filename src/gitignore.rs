@@ -0,0 +1,173 @@
+// src/gitignore.rs
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// An entry that a repository's `.gitignore` needs to ignore a directory.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MissingEntry {
+    /// The `.gitignore` file at the root of the repository.
+    pub gitignore: PathBuf,
+    /// The directory, relative to the repository root, as a `/dir/` pattern.
+    pub entry: String,
+}
+
+impl MissingEntry {
+    /// Appends the entry to the `.gitignore` file, creating it if needed.
+    pub fn add(&self) -> io::Result<()> {
+        let mut content = fs::read_to_string(&self.gitignore).unwrap_or_default();
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&self.entry);
+        content.push('\n');
+        fs::write(&self.gitignore, content)
+    }
+}
+
+/// Returns the `.gitignore` entry needed to keep `dir` out of commits: `None` when `dir` is not
+/// inside a git repository, is the repository itself, or is already listed in the `.gitignore`
+/// at the repository root. Patterns matching `dir` indirectly, such as wildcards or ignored
+/// parents, are not recognized.
+///
+/// # Arguments
+/// * `dir` - The directory, absolute or relative to `project_dir`. It does not need to exist.
+/// * `project_dir` - The project directory.
+pub fn missing_entry(dir: &Path, project_dir: &Path) -> Option<MissingEntry> {
+    let dir = project_dir.join(dir);
+    let dir = dir.canonicalize().unwrap_or(dir);
+    let root = repository_root(dir.parent()?)?;
+    let relative: Vec<String> = dir
+        .strip_prefix(root)
+        .ok()?
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    if relative.is_empty() {
+        return None;
+    }
+    let relative = relative.join("/");
+
+    let gitignore = root.join(".gitignore");
+    let listed = fs::read_to_string(&gitignore)
+        .unwrap_or_default()
+        .lines()
+        .any(|line| line.trim().trim_start_matches('/').trim_end_matches('/') == relative);
+    (!listed).then(|| MissingEntry {
+        gitignore,
+        entry: format!("/{}/", relative),
+    })
+}
+
+/// The ignore rules of a git repository applying to a walked directory: the global excludes
+/// file, `.git/info/exclude`, and the `.gitignore` files from the repository root down to the
+/// directory being visited.
+pub struct IgnoreRules {
+    /// Matchers from the least to the most specific. The most specific match wins.
+    matchers: Vec<Gitignore>,
+}
+
+impl IgnoreRules {
+    /// Returns the rules applying to `dir`, an absolute path, or `None` when it is not inside a
+    /// git repository. Visited paths must share the prefix of `dir` to be matched.
+    pub fn for_dir(dir: &Path) -> Option<IgnoreRules> {
+        let root = repository_root(dir)?.to_path_buf();
+        let mut rules = IgnoreRules {
+            matchers: vec![Gitignore::global().0, matcher(&root, &root.join(".git").join("info").join("exclude"))],
+        };
+        let mut ancestors: Vec<&Path> = dir.ancestors().take_while(|ancestor| ancestor.starts_with(&root)).collect();
+        ancestors.reverse();
+        for ancestor in ancestors {
+            rules.enter(ancestor);
+        }
+        Some(rules)
+    }
+
+    /// Adds the `.gitignore` of `dir`, about to be visited. Must be paired with [`leave`](Self::leave).
+    pub fn enter(&mut self, dir: &Path) {
+        self.matchers.push(matcher(dir, &dir.join(".gitignore")));
+    }
+
+    /// Drops the rules of the last directory entered.
+    pub fn leave(&mut self) {
+        self.matchers.pop();
+    }
+
+    /// Returns whether `path`, inside the directories entered, is ignored.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.matchers
+            .iter()
+            .rev()
+            .map(|matcher| matcher.matched(path, is_dir))
+            .find(|matched| !matched.is_none())
+            .is_some_and(|matched| matched.is_ignore())
+    }
+}
+
+/// Builds a matcher for the ignore file at `path`, relative to `root`. A missing or invalid
+/// file matches nothing.
+fn matcher(root: &Path, path: &Path) -> Gitignore {
+    if !path.is_file() {
+        return Gitignore::empty();
+    }
+    let mut builder = GitignoreBuilder::new(root);
+    if let Some(e) = builder.add(path) {
+        eprintln!("Warning: ignoring invalid rules in {}: {}", path.display(), e);
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// Returns the closest directory containing `path` that has a `.git` entry.
+fn repository_root(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|ancestor| ancestor.join(".git").exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_missing_entry() {
+        let repository = TempDir::new("gitignore");
+        let project_dir = repository.join("app");
+        fs::create_dir_all(repository.join(".git")).unwrap();
+        fs::create_dir_all(&project_dir).unwrap();
+        fs::write(repository.join(".gitignore"), "target").unwrap();
+
+        let missing = missing_entry(Path::new("prompts/out"), &project_dir).unwrap();
+        assert_eq!(missing.entry, "/app/prompts/out/");
+        missing.add().unwrap();
+        assert_eq!(fs::read_to_string(repository.join(".gitignore")).unwrap(), "target\n/app/prompts/out/\n");
+        assert_eq!(missing_entry(Path::new("prompts/out"), &project_dir), None);
+        assert_eq!(missing_entry(Path::new("target"), &repository), None);
+        assert_eq!(missing_entry(&std::env::temp_dir(), &project_dir), None);
+    }
+
+    #[test]
+    fn test_ignore_rules() {
+        let repository = TempDir::new("ignore-rules");
+        fs::create_dir_all(repository.join(".git/info")).unwrap();
+        fs::create_dir_all(repository.join("app/src")).unwrap();
+        fs::write(repository.join(".gitignore"), "*.log\n").unwrap();
+        fs::write(repository.join(".git/info/exclude"), "scratch/\n").unwrap();
+        fs::write(repository.join("app/src/.gitignore"), "generated.rs\n!keep.log\n").unwrap();
+
+        let app = repository.join("app").canonicalize().unwrap();
+        let mut rules = IgnoreRules::for_dir(&app).unwrap();
+        assert!(rules.is_ignored(&app.join("debug.log"), false));
+        assert!(rules.is_ignored(&app.join("scratch"), true));
+        assert!(!rules.is_ignored(&app.join("src"), true));
+        rules.enter(&app.join("src"));
+        assert!(rules.is_ignored(&app.join("src/generated.rs"), false));
+        assert!(!rules.is_ignored(&app.join("src/keep.log"), false));
+        rules.leave();
+        assert!(!rules.is_ignored(&app.join("generated.rs"), false));
+    }
+}
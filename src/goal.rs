@@ -0,0 +1,93 @@
+// src/goal.rs
+
+use std::io::{self, IsTerminal, Write};
+
+use crossterm::cursor::MoveToColumn;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::queue;
+
+use crate::tokens::Tokenizer;
+
+/// Reads the goal from the terminal, showing a live count of the total prompt tokens (the
+/// already generated context plus the goal typed so far).
+///
+/// When stdin is not a terminal, the goal is the rest of stdin, so that multi-line goals can
+/// be piped in (`prompt-gen < goal.txt`).
+///
+/// # Arguments
+/// * `tokenizer` - The configured tokenizer, which counts the goal typed so far.
+/// * `context_tokens` - The token count of the prompt without the goal.
+/// * `writer` - Where the input line and its counter are drawn.
+///
+/// # Returns
+/// The trimmed goal entered by the user.
+pub fn read_goal<W: Write>(tokenizer: &dyn Tokenizer, context_tokens: usize, mut writer: W) -> io::Result<String> {
+    if !io::stdin().is_terminal() {
+        return Ok(io::read_to_string(io::stdin())?.trim().to_string());
+    }
+
+    terminal::enable_raw_mode()?;
+    let result = read_goal_raw(tokenizer, context_tokens, &mut writer);
+    terminal::disable_raw_mode()?;
+    writeln!(writer)?;
+
+    match result {
+        Ok(Some(goal)) => Ok(goal.trim().to_string()),
+        Ok(None) => Err(io::Error::new(io::ErrorKind::Interrupted, "Goal input interrupted")),
+        Err(e) => Err(e),
+    }
+}
+
+/// Asks for the value of the `name` placeholder of a goal template and reads it as a line.
+///
+/// # Returns
+/// The trimmed value entered by the user.
+pub fn read_placeholder<W: Write>(name: &str, mut writer: W) -> io::Result<String> {
+    write!(writer, "{}: ", name)?;
+    writer.flush()?;
+    let mut value = String::new();
+    io::stdin().read_line(&mut value)?;
+    Ok(value.trim().to_string())
+}
+
+/// Reads keystrokes until Enter is pressed, redrawing the token counter after each change.
+/// Returns `None` when the user interrupts the input with Ctrl-C or Esc.
+fn read_goal_raw<W: Write>(tokenizer: &dyn Tokenizer, context_tokens: usize, writer: &mut W) -> io::Result<Option<String>> {
+    let mut goal = String::new();
+    draw_goal_line(writer, tokenizer, context_tokens, &goal)?;
+
+    loop {
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => return Ok(Some(goal)),
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+            KeyCode::Char(c) => goal.push(c),
+            KeyCode::Backspace => {
+                goal.pop();
+            }
+            _ => continue,
+        }
+        draw_goal_line(writer, tokenizer, context_tokens, &goal)?;
+    }
+}
+
+/// Redraws the input line as `[~N tokens] > goal`, keeping the end of the goal visible
+/// when it is wider than the terminal.
+fn draw_goal_line<W: Write>(writer: &mut W, tokenizer: &dyn Tokenizer, context_tokens: usize, goal: &str) -> io::Result<()> {
+    let counter = format!("[~{} tokens] > ", context_tokens + tokenizer.count(goal));
+    let width = terminal::size().map(|(columns, _)| columns as usize).unwrap_or(80);
+    let available = width.saturating_sub(counter.chars().count() + 1);
+    let skip = goal.chars().count().saturating_sub(available);
+    let visible: String = goal.chars().skip(skip).collect();
+
+    queue!(writer, MoveToColumn(0), Clear(ClearType::CurrentLine))?;
+    write!(writer, "{}{}", counter, visible)?;
+    writer.flush()
+}
@@ -0,0 +1,126 @@
+// src/group.rs
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::{build_context, timeout_warning, BuildOptions, Context};
+
+/// Builds the context of a prompt spanning the projects of a group, for questions that cut
+/// across repositories.
+///
+/// The introductory prompt of the first project comes first, then each project under a
+/// `Project: NAME` header, walked and cleaned with its own configuration. The scaffolding
+/// strings and answer instructions are those of the first project.
+///
+/// # Arguments
+/// * `projects` - The directory and configuration of each project, in prompt order.
+/// * `timeout` - Time allowed for the whole group, shared by its projects.
+/// * `allow_outside_root` - Whether roots and symbolic links leaving a project are followed.
+pub fn build_group_context(projects: &[(PathBuf, Config)], timeout: Option<Duration>, allow_outside_root: bool) -> io::Result<Context> {
+    let started = Instant::now();
+    let Some((_, first)) = projects.first() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the group has no project"));
+    };
+
+    let mut content = Vec::new();
+    writeln!(content, "{}", first.intro_prompt)?;
+    let mut files = Vec::new();
+    let mut trees = String::new();
+    let mut timed_out = false;
+    let mut redactions = Vec::new();
+    let mut warnings = Vec::new();
+    for (project_dir, config) in projects {
+        let options = BuildOptions {
+            timeout: timeout.map(|timeout| timeout.saturating_sub(started.elapsed())),
+            allow_outside_root,
+            skip_intro: true,
+            ..Default::default()
+        };
+        let context = build_context(config, project_dir, &options)?;
+        writeln!(content, "{}: {}", first.strings().project, config.project_name)?;
+        content.extend(context.content);
+        files.extend(context.files);
+        trees.push_str(&context.trees);
+        timed_out |= context.timed_out;
+        redactions.extend(context.redactions.into_iter().map(|mut redaction| {
+            redaction.path = format!("{}/{}", config.project_name, redaction.path);
+            redaction
+        }));
+        // The timeout is reported for the whole group below, not what was left of it
+        let project_timeout = options.timeout.map(timeout_warning);
+        warnings.extend(context.warnings.into_iter().filter(|warning| Some(warning) != project_timeout.as_ref()));
+    }
+    if let (true, Some(timeout)) = (timed_out, timeout) {
+        warnings.push(timeout_warning(timeout));
+    }
+
+    Ok(Context {
+        content,
+        files,
+        trees,
+        strings: first.strings(),
+        answer_in_language: first.answer_in_language,
+        answer_with_diff: first.answer_with_diff,
+        outro_prompt: first.outro_prompt.clone(),
+        timed_out,
+        exclusions: None,
+        images: Vec::new(),
+        redactions,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_build_group_context() {
+        let group_dir = TempDir::new("group");
+        fs::create_dir_all(group_dir.join("api")).unwrap();
+        fs::create_dir_all(group_dir.join("web")).unwrap();
+        fs::write(group_dir.join("api/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(group_dir.join("api/app.ts"), "export {};\n").unwrap();
+        fs::write(group_dir.join("web/app.ts"), "export {};\n").unwrap();
+        let api = Config {
+            project_name: "api".to_string(),
+            intro_prompt: "Intro".to_string(),
+            allowed_extensions: vec!["rs".to_string()],
+            deterministic: true,
+            ..Default::default()
+        };
+        let web = Config {
+            project_name: "web".to_string(),
+            intro_prompt: "Ignored".to_string(),
+            allowed_extensions: vec!["ts".to_string()],
+            deterministic: true,
+            ..Default::default()
+        };
+
+        // Each project keeps its own filters
+        let projects = vec![(group_dir.join("api"), api), (group_dir.join("web"), web)];
+        let context = build_group_context(&projects, None, false).unwrap();
+        assert_eq!(
+            String::from_utf8(context.content).unwrap(),
+            "Intro\nProject: api\nFile: main.rs\n```\nfn main() {}\n```\n.\n└── main.rs\n\n\
+             Project: web\nFile: app.ts\n```\nexport {};\n```\n.\n└── app.ts\n\n"
+        );
+        let paths: Vec<&str> = context.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["main.rs", "app.ts"]);
+        assert!(context.warnings.is_empty());
+
+        // A timeout is reported once, for the whole group
+        let projects: Vec<(PathBuf, Config)> = projects
+            .into_iter()
+            .map(|(project_dir, config)| (project_dir, Config { deterministic: false, ..config }))
+            .collect();
+        let context = build_group_context(&projects, Some(Duration::ZERO), false).unwrap();
+        assert!(context.timed_out);
+        assert_eq!(context.warnings, vec!["generation timed out after 0ns, the prompt is partial".to_string()]);
+    }
+}
@@ -0,0 +1,184 @@
+// src/i18n.rs
+
+use std::time::Duration;
+
+/// Scaffolding strings of the generated prompts, in one language.
+pub struct Strings {
+    /// Header preceding each file content.
+    pub file: &'static str,
+    /// Label of the symbol counts line.
+    pub stats: &'static str,
+    /// Header of each configured root in the tree.
+    pub root: &'static str,
+    /// Title of the build and CI files section.
+    pub build_section: &'static str,
+    /// Title of the `--git-diff` section, followed by the git ref.
+    pub changes_since: &'static str,
+    /// Title of the `--compare` diff section, followed by the revision range.
+    pub changes_between: &'static str,
+    /// Title of the project summary included with `--changed-only`.
+    pub project_summary: &'static str,
+    /// Title of the `--staged` diff section.
+    pub staged_changes: &'static str,
+    /// Default introductory prompt of `--staged` prompts.
+    pub staged_intro: &'static str,
+    /// Goal of the general review preset, used for empty goals with `empty_goal = "review"`.
+    pub review_goal: &'static str,
+    /// Header of the sections added with `--append-to`.
+    pub section: &'static str,
+    /// Header of each project of a `--group` prompt.
+    pub project: &'static str,
+    /// Line following the header of files matching `reference_globs`.
+    pub reference_only: &'static str,
+    /// Label of the tree section in bundles.
+    pub files: &'static str,
+    /// Title of the image list added with `--with-images`.
+    pub images: &'static str,
+    pub specific_goal: &'static str,
+    pub previous_goal: &'static str,
+    pub follow_up: &'static str,
+    /// First line of partial prompts, where `{timeout}` is replaced by the timeout.
+    partial_notice: &'static str,
+    /// Instruction asking the model to answer in this language.
+    pub answer_instruction: &'static str,
+    /// Instruction asking the model to answer with a patch against the files of the prompt.
+    pub diff_instruction: &'static str,
+}
+
+impl Strings {
+    /// Returns the line marking a prompt whose generation was stopped by `--timeout`.
+    pub fn partial_notice(&self, timeout: Duration) -> String {
+        self.partial_notice.replace("{timeout}", &format!("{:?}", timeout))
+    }
+}
+
+const ENGLISH: Strings = Strings {
+    file: "File",
+    stats: "Stats",
+    root: "Root",
+    build_section: "Build & CI",
+    changes_since: "Changes since",
+    changes_between: "Changes between",
+    project_summary: "Project summary",
+    staged_changes: "Staged changes",
+    staged_intro: "Here are the changes staged for commit in this project: the current content of each staged file, the project tree, then the staged diff. Review these changes or write a commit message for them, as asked below.",
+    review_goal: "Review this project: point out bugs, risky or unclear code, and possible improvements, the most important first.",
+    section: "Section",
+    project: "Project",
+    reference_only: "Reference only — do not modify",
+    files: "Files",
+    images: "Images",
+    specific_goal: "Specific Goal",
+    previous_goal: "Previous Goal",
+    follow_up: "Follow-up",
+    partial_notice: "[Partial prompt: generation stopped after the {timeout} timeout, some files were not processed.]",
+    answer_instruction: "Please answer in English.",
+    diff_instruction: "Answer with a unified diff (as produced by `git diff`) that applies to the files above: use their paths exactly as given in this prompt in the `--- a/PATH` and `+++ b/PATH` headers, with three lines of context around each change. Use `/dev/null` as the old path of new files.",
+};
+
+const FRENCH: Strings = Strings {
+    file: "Fichier",
+    stats: "Statistiques",
+    root: "Racine",
+    build_section: "Build et CI",
+    changes_since: "Modifications depuis",
+    changes_between: "Modifications entre",
+    project_summary: "Résumé du projet",
+    staged_changes: "Modifications indexées",
+    staged_intro: "Voici les modifications indexées pour le prochain commit de ce projet : le contenu actuel de chaque fichier indexé, l'arborescence du projet, puis le diff indexé. Relis ces modifications ou rédige un message de commit pour elles, comme demandé ci-dessous.",
+    review_goal: "Relis ce projet : signale les bogues, le code risqué ou peu clair et les améliorations possibles, les plus importants d'abord.",
+    section: "Section",
+    project: "Projet",
+    reference_only: "Référence uniquement — ne pas modifier",
+    files: "Fichiers",
+    images: "Images",
+    specific_goal: "Objectif",
+    previous_goal: "Objectif précédent",
+    follow_up: "Suite",
+    partial_notice: "[Prompt partiel : génération arrêtée après le délai de {timeout}, certains fichiers n'ont pas été traités.]",
+    answer_instruction: "Merci de répondre en français.",
+    diff_instruction: "Réponds avec un diff unifié (tel que produit par `git diff`) applicable aux fichiers ci-dessus : reprends leurs chemins exactement comme ils figurent dans ce prompt dans les en-têtes `--- a/CHEMIN` et `+++ b/CHEMIN`, avec trois lignes de contexte autour de chaque modification. Utilise `/dev/null` comme ancien chemin des nouveaux fichiers.",
+};
+
+const GERMAN: Strings = Strings {
+    file: "Datei",
+    stats: "Statistik",
+    root: "Wurzel",
+    build_section: "Build & CI",
+    changes_since: "Änderungen seit",
+    changes_between: "Änderungen zwischen",
+    project_summary: "Projektübersicht",
+    staged_changes: "Vorgemerkte Änderungen",
+    staged_intro: "Hier sind die für den nächsten Commit vorgemerkten Änderungen dieses Projekts: der aktuelle Inhalt jeder vorgemerkten Datei, der Projektbaum und danach der vorgemerkte Diff. Prüfe diese Änderungen oder schreibe eine Commit-Nachricht dafür, wie unten verlangt.",
+    review_goal: "Prüfe dieses Projekt: Weise auf Fehler, riskanten oder unklaren Code und mögliche Verbesserungen hin, die wichtigsten zuerst.",
+    section: "Abschnitt",
+    project: "Projekt",
+    reference_only: "Nur als Referenz — nicht ändern",
+    files: "Dateien",
+    images: "Bilder",
+    specific_goal: "Konkretes Ziel",
+    previous_goal: "Vorheriges Ziel",
+    follow_up: "Folgeauftrag",
+    partial_notice: "[Unvollständiger Prompt: Generierung nach dem Zeitlimit von {timeout} abgebrochen, einige Dateien wurden nicht verarbeitet.]",
+    answer_instruction: "Bitte antworte auf Deutsch.",
+    diff_instruction: "Antworte mit einem Unified Diff (wie von `git diff` erzeugt), der auf die obigen Dateien anwendbar ist: Verwende ihre Pfade genau wie in diesem Prompt angegeben in den Kopfzeilen `--- a/PFAD` und `+++ b/PFAD`, mit drei Kontextzeilen um jede Änderung. Verwende `/dev/null` als alten Pfad neuer Dateien.",
+};
+
+const SPANISH: Strings = Strings {
+    file: "Archivo",
+    stats: "Estadísticas",
+    root: "Raíz",
+    build_section: "Build y CI",
+    changes_since: "Cambios desde",
+    changes_between: "Cambios entre",
+    project_summary: "Resumen del proyecto",
+    staged_changes: "Cambios preparados",
+    staged_intro: "Estos son los cambios preparados para el próximo commit de este proyecto: el contenido actual de cada archivo preparado, el árbol del proyecto y después el diff preparado. Revisa estos cambios o escribe un mensaje de commit para ellos, según se pide a continuación.",
+    review_goal: "Revisa este proyecto: señala los errores, el código arriesgado o poco claro y las posibles mejoras, los más importantes primero.",
+    section: "Sección",
+    project: "Proyecto",
+    reference_only: "Solo referencia — no modificar",
+    files: "Archivos",
+    images: "Imágenes",
+    specific_goal: "Objetivo específico",
+    previous_goal: "Objetivo anterior",
+    follow_up: "Seguimiento",
+    partial_notice: "[Prompt parcial: la generación se detuvo tras el límite de {timeout}, algunos archivos no se procesaron.]",
+    answer_instruction: "Por favor, responde en español.",
+    diff_instruction: "Responde con un diff unificado (como el que produce `git diff`) aplicable a los archivos anteriores: usa sus rutas exactamente como aparecen en este prompt en las cabeceras `--- a/RUTA` y `+++ b/RUTA`, con tres líneas de contexto alrededor de cada cambio. Usa `/dev/null` como ruta antigua de los archivos nuevos.",
+};
+
+/// Languages with translated scaffolding, by ISO 639-1 code.
+const LANGUAGES: &[(&str, &Strings)] = &[("en", &ENGLISH), ("fr", &FRENCH), ("de", &GERMAN), ("es", &SPANISH)];
+
+/// Returns the scaffolding strings for `language`, an ISO 639-1 code such as `fr`. English is
+/// used when no language is set or when it has no translation.
+pub fn strings(language: Option<&str>) -> &'static Strings {
+    language
+        .and_then(|language| LANGUAGES.iter().find(|(code, _)| code.eq_ignore_ascii_case(language)))
+        .map_or(&ENGLISH, |(_, strings)| *strings)
+}
+
+/// Returns whether the scaffolding of `language` is translated.
+pub fn is_supported(language: &str) -> bool {
+    LANGUAGES.iter().any(|(code, _)| code.eq_ignore_ascii_case(language))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(strings(Some("fr")).specific_goal, "Objectif");
+        assert_eq!(strings(Some("DE")).file, "Datei");
+        assert_eq!(strings(Some("tlh")).specific_goal, "Specific Goal");
+        assert_eq!(strings(None).specific_goal, "Specific Goal");
+        assert!(!is_supported("tlh"));
+        assert!(LANGUAGES.iter().all(|(_, strings)| strings.diff_instruction.contains("`--- a/")));
+        assert_eq!(
+            strings(None).partial_notice(Duration::from_secs(30)),
+            "[Partial prompt: generation stopped after the 30s timeout, some files were not processed.]"
+        );
+    }
+}
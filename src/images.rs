@@ -0,0 +1,209 @@
+// src/images.rs
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{format_size, i18n};
+
+/// Extensions of the files listed with `--with-images`.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "ico"];
+
+/// Number of bytes read at the start of an image to find its dimensions. JPEG files may put
+/// their EXIF metadata before the frame header, so this leaves room for it.
+const HEADER_BYTES: u64 = 128 * 1024;
+
+/// Largest image embedded in the chat messages with `--embed-images`.
+pub const MAX_EMBEDDED_BYTES: u64 = 256 * 1024;
+
+/// An image file of the project, listed in the images section of the prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    /// Path as shown in the prompt.
+    pub path: String,
+    /// Location of the file on disk.
+    pub source: PathBuf,
+    /// Size of the file, in bytes.
+    pub size: u64,
+    /// Width and height in pixels, when the format is known.
+    pub dimensions: Option<(u32, u32)>,
+}
+
+impl Image {
+    /// Reads the size and dimensions of the image at `source`.
+    pub fn read(path: String, source: &Path) -> io::Result<Image> {
+        let size = fs::metadata(source)?.len();
+        let mut header = Vec::new();
+        fs::File::open(source)?.take(HEADER_BYTES).read_to_end(&mut header)?;
+        Ok(Image {
+            path,
+            source: source.to_path_buf(),
+            size,
+            dimensions: dimensions(&header),
+        })
+    }
+
+    /// Returns the media type of the image, when it is one chat models accept as input.
+    pub fn media_type(&self) -> Option<&'static str> {
+        match extension(&self.source).as_deref() {
+            Some("png") => Some("image/png"),
+            Some("jpg" | "jpeg") => Some("image/jpeg"),
+            Some("gif") => Some("image/gif"),
+            Some("webp") => Some("image/webp"),
+            _ => None,
+        }
+    }
+
+    /// Returns the image as a `data:` URL, for the image parts of chat messages.
+    pub fn data_url(&self) -> io::Result<Option<String>> {
+        let Some(media_type) = self.media_type() else {
+            return Ok(None);
+        };
+        Ok(Some(format!("data:{};base64,{}", media_type, base64(&fs::read(&self.source)?))))
+    }
+}
+
+/// Returns whether the file at `path` is an image, from its extension.
+pub fn is_image(path: &Path) -> bool {
+    extension(path).is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.as_str()))
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension().map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+}
+
+/// Writes the images section: one line per image with its dimensions and size, e.g.
+/// `- assets/logo.png (512x512, 12.3 kB)`.
+pub fn write_section<W: Write>(prompt_file: &mut W, strings: &i18n::Strings, images: &[Image]) -> io::Result<()> {
+    writeln!(prompt_file, "{}:", strings.images)?;
+    for image in images {
+        match image.dimensions {
+            Some((width, height)) => writeln!(prompt_file, "- {} ({}x{}, {})", image.path, width, height, format_size(image.size))?,
+            None => writeln!(prompt_file, "- {} ({})", image.path, format_size(image.size))?,
+        }
+    }
+    Ok(())
+}
+
+/// Returns the width and height of a PNG, GIF, JPEG, WebP or BMP image from the start of its
+/// content.
+fn dimensions(header: &[u8]) -> Option<(u32, u32)> {
+    let u16_le = |at: usize| header.get(at..at + 2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as u32);
+    let u16_be = |at: usize| header.get(at..at + 2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as u32);
+    let u32_le = |at: usize| header.get(at..at + 4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    let u32_be = |at: usize| header.get(at..at + 4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+
+    if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some((u32_be(16)?, u32_be(20)?));
+    }
+    if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+        return Some((u16_le(6)?, u16_le(8)?));
+    }
+    if header.starts_with(b"BM") {
+        // Bottom-up images store a negative height
+        return Some((u32_le(18)?, (u32_le(22)? as i32).unsigned_abs()));
+    }
+    if header.starts_with(b"RIFF") && header.get(8..12) == Some(&b"WEBP"[..]) {
+        return match header.get(12..16)? {
+            b"VP8 " => Some((u16_le(26)? & 0x3fff, u16_le(28)? & 0x3fff)),
+            b"VP8L" => {
+                let bits = u32_le(21)?;
+                Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => {
+                let width = u32_le(24)? & 0xff_ffff;
+                let height = u32_le(27)? & 0xff_ffff;
+                Some((width + 1, height + 1))
+            }
+            _ => None,
+        };
+    }
+    if header.starts_with(&[0xff, 0xd8]) {
+        // Walk the segments up to a start-of-frame marker, which holds the dimensions
+        let mut at = 2;
+        while *header.get(at)? == 0xff {
+            let marker = *header.get(at + 1)?;
+            if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+                return Some((u16_be(at + 7)?, u16_be(at + 5)?));
+            }
+            at += 2 + u16_be(at + 2)? as usize;
+        }
+    }
+    None
+}
+
+/// Encodes `bytes` in standard base64, with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = ((chunk[0] as u32) << 16) | ((*chunk.get(1).unwrap_or(&0) as u32) << 8) | (*chunk.get(2).unwrap_or(&0) as u32);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((triple >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        assert_eq!(dimensions(&png), Some((640, 480)));
+
+        let gif = b"GIF89a\x20\x00\x10\x00";
+        assert_eq!(dimensions(gif), Some((32, 16)));
+
+        // An APP0 segment, then the start-of-frame segment
+        let jpeg = [
+            0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, 0xff, 0xc0, 0x00, 0x11, 0x08, 0x00, 0x64, 0x00, 0xc8,
+        ];
+        assert_eq!(dimensions(&jpeg), Some((200, 100)));
+
+        assert_eq!(dimensions(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"), None);
+        assert_eq!(dimensions(b"\x89PNG\r\n\x1a\n"), None);
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_write_section() {
+        let images = vec![
+            Image {
+                path: "assets/logo.png".to_string(),
+                source: PathBuf::from("assets/logo.png"),
+                size: 12_345,
+                dimensions: Some((512, 512)),
+            },
+            Image {
+                path: "docs/diagram.svg".to_string(),
+                source: PathBuf::from("docs/diagram.svg"),
+                size: 900,
+                dimensions: None,
+            },
+        ];
+        let mut section = Vec::new();
+        write_section(&mut section, i18n::strings(None), &images).unwrap();
+        assert_eq!(String::from_utf8(section).unwrap(), "Images:\n- assets/logo.png (512x512, 12.3 kB)\n- docs/diagram.svg (900 bytes)\n");
+        assert!(is_image(Path::new("assets/Logo.PNG")));
+        assert!(!is_image(Path::new("src/main.rs")));
+        assert_eq!(images[0].media_type(), Some("image/png"));
+        assert_eq!(images[1].media_type(), None);
+    }
+}
@@ -0,0 +1,341 @@
+// src/init.rs
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{Confirm, Input, MultiSelect, Select};
+
+use crate::config::{Config, DEFAULT_OUTPUT_DIR};
+use crate::gitignore;
+
+/// Directories commonly holding build outputs, dependencies or tooling state.
+const KNOWN_DENY_DIRS: &[&str] = &[
+    ".git", ".hg", ".svn", ".idea", ".vscode", "target", "node_modules", "dist", "build", "out",
+    ".venv", "venv", "__pycache__", ".mypy_cache", ".pytest_cache", ".next", ".terraform", "vendor",
+];
+
+/// Extensions of source and configuration files, pre-selected when found in the project.
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "rs", "toml", "c", "h", "cc", "cpp", "hpp", "py", "js", "jsx", "ts", "tsx", "go", "java", "kt",
+    "rb", "sh", "sql", "swift", "cs", "php", "scala", "ex", "exs", "hs", "lua", "zig",
+];
+
+/// Number of directory entries inspected when detecting extensions, to keep the interview fast.
+const DETECTION_LIMIT: usize = 20_000;
+
+/// Answers that can be revisited before the configuration is saved.
+#[derive(Clone, Copy)]
+enum Field {
+    ProjectName,
+    OutputPath,
+    IntroPrompt,
+    AllowedExtensions,
+    DenyDirs,
+}
+
+const FIELDS: [Field; 5] = [Field::ProjectName, Field::OutputPath, Field::IntroPrompt, Field::AllowedExtensions, Field::DenyDirs];
+
+/// Builds the configuration the interview would propose for the project at `project_dir`,
+/// without asking anything: the detected source extensions, the detected build and tooling
+/// directories, and the default output path.
+pub fn detected_defaults(project_dir: &Path) -> Config {
+    Config {
+        project_name: default_project_name(project_dir),
+        allowed_extensions: detect_extensions(project_dir)
+            .into_iter()
+            .map(|(extension, _)| extension)
+            .filter(|extension| SOURCE_EXTENSIONS.contains(&extension.as_str()))
+            .collect(),
+        deny_dirs: detect_deny_dirs(project_dir),
+        ..Default::default()
+    }
+}
+
+fn default_project_name(project_dir: &Path) -> String {
+    project_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| project_dir.display().to_string())
+}
+
+/// Runs the interactive configuration interview for the project at `project_dir`.
+///
+/// Extensions and deny directories are offered as multi-select lists built from what is found
+/// in the project, the output path is validated (and created if needed), and every answer can
+/// be edited from a summary before the configuration is accepted.
+pub fn interview(project_dir: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+    let detected_extensions = detect_extensions(project_dir);
+    let detected_deny_dirs = detect_deny_dirs(project_dir);
+    let theme = ColorfulTheme::default();
+
+    let mut config = Config {
+        project_name: default_project_name(project_dir),
+        ..Default::default()
+    };
+    for field in FIELDS {
+        ask(field, &mut config, project_dir, &detected_extensions, &detected_deny_dirs, &theme)?;
+    }
+
+    loop {
+        let mut items = vec!["Save this configuration".to_string()];
+        items.extend(FIELDS.iter().map(|&field| format!("Edit {}: {}", label(field), summary(field, &config))));
+        let choice = Select::with_theme(&theme)
+            .with_prompt("Review the configuration")
+            .items(&items)
+            .default(0)
+            .interact()?;
+        if choice == 0 {
+            offer_gitignore(&config, project_dir, &theme)?;
+            return Ok(config);
+        }
+        ask(FIELDS[choice - 1], &mut config, project_dir, &detected_extensions, &detected_deny_dirs, &theme)?;
+    }
+}
+
+fn label(field: Field) -> &'static str {
+    match field {
+        Field::ProjectName => "project name",
+        Field::OutputPath => "output path",
+        Field::IntroPrompt => "introductory prompt",
+        Field::AllowedExtensions => "allowed extensions",
+        Field::DenyDirs => "directories to ignore",
+    }
+}
+
+fn summary(field: Field, config: &Config) -> String {
+    match field {
+        Field::ProjectName => config.project_name.clone(),
+        Field::OutputPath if config.output_path.is_empty() => format!("{} (default)", DEFAULT_OUTPUT_DIR),
+        Field::OutputPath => config.output_path.clone(),
+        Field::IntroPrompt => config.intro_prompt.chars().take(40).collect(),
+        Field::AllowedExtensions => config.allowed_extensions.join(","),
+        Field::DenyDirs => config.deny_dirs.join(","),
+    }
+}
+
+fn ask(
+    field: Field,
+    config: &mut Config,
+    project_dir: &Path,
+    detected_extensions: &[(String, usize)],
+    detected_deny_dirs: &[String],
+    theme: &ColorfulTheme,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match field {
+        Field::ProjectName => {
+            config.project_name = Input::with_theme(theme)
+                .with_prompt("Project name")
+                .with_initial_text(config.project_name.clone())
+                .interact_text()?;
+        }
+        Field::OutputPath => config.output_path = ask_output_path(config, project_dir, theme)?,
+        Field::IntroPrompt => {
+            config.intro_prompt = Input::with_theme(theme)
+                .with_prompt("Introductory prompt")
+                .with_initial_text(config.intro_prompt.clone())
+                .allow_empty(true)
+                .interact_text()?;
+        }
+        Field::AllowedExtensions => {
+            let choices: Vec<(String, String)> = detected_extensions
+                .iter()
+                .map(|(extension, count)| (extension.clone(), format!("{} ({} files)", extension, count)))
+                .collect();
+            let preselected = |extension: &str| {
+                if config.allowed_extensions.is_empty() {
+                    SOURCE_EXTENSIONS.contains(&extension)
+                } else {
+                    config.allowed_extensions.iter().any(|e| e == extension)
+                }
+            };
+            config.allowed_extensions = ask_list("Allowed file extensions", &choices, preselected, &config.allowed_extensions, theme)?;
+        }
+        Field::DenyDirs => {
+            let choices: Vec<(String, String)> = detected_deny_dirs.iter().map(|dir| (dir.clone(), dir.clone())).collect();
+            let first_time = config.deny_dirs.is_empty();
+            let preselected = |dir: &str| first_time || config.deny_dirs.iter().any(|d| d == dir);
+            config.deny_dirs = ask_list("Directories to ignore", &choices, preselected, &config.deny_dirs, theme)?;
+        }
+    }
+    Ok(())
+}
+
+/// Offers to add the output directory to the repository's `.gitignore` when it lies inside the
+/// repository. The default `.prompts` directory ignores itself.
+fn offer_gitignore(config: &Config, project_dir: &Path, theme: &ColorfulTheme) -> Result<(), Box<dyn std::error::Error>> {
+    if config.output_path.is_empty() {
+        return Ok(());
+    }
+    let Some(missing) = gitignore::missing_entry(&config.output_dir(project_dir), project_dir) else {
+        return Ok(());
+    };
+    let add = Confirm::with_theme(theme)
+        .with_prompt(format!("Add {} to {}?", missing.entry, missing.gitignore.display()))
+        .default(true)
+        .interact()?;
+    if add {
+        missing.add()?;
+    }
+    Ok(())
+}
+
+/// Asks for the output directory until it is a directory, or a missing one the user agrees to
+/// create. An empty answer selects the default `.prompts` directory of the project. Answers are
+/// checked and created where prompts will be written: relative paths in `project_dir`, whatever
+/// the working directory.
+fn ask_output_path(config: &Config, project_dir: &Path, theme: &ColorfulTheme) -> Result<String, Box<dyn std::error::Error>> {
+    loop {
+        let output_path: String = Input::with_theme(theme)
+            .with_prompt(format!("Output path (empty for {} in the project)", DEFAULT_OUTPUT_DIR))
+            .with_initial_text(config.output_path.clone())
+            .allow_empty(true)
+            .validate_with(|input: &String| -> Result<(), &str> {
+                let path = answered_output_dir(config, project_dir, input.trim());
+                if !input.trim().is_empty() && path.exists() && !path.is_dir() {
+                    Err("The output path must be a directory")
+                } else {
+                    Ok(())
+                }
+            })
+            .interact_text()?;
+        let output_path = output_path.trim().to_string();
+        let output_dir = answered_output_dir(config, project_dir, &output_path);
+
+        if output_path.is_empty() || output_dir.is_dir() {
+            return Ok(output_path);
+        }
+        let create = Confirm::with_theme(theme)
+            .with_prompt(format!("{} does not exist, create it?", output_dir.display()))
+            .default(true)
+            .interact()?;
+        if create {
+            fs::create_dir_all(&output_dir)?;
+            return Ok(output_path);
+        }
+    }
+}
+
+/// Returns the directory prompts would be written to with `answer` as the output path.
+fn answered_output_dir(config: &Config, project_dir: &Path, answer: &str) -> PathBuf {
+    Config {
+        output_path: answer.to_string(),
+        ..config.clone()
+    }
+    .output_dir(project_dir)
+}
+
+/// Offers `choices` (value, display label) as a multi-select, then lets the user add values
+/// that were not detected, as a comma-separated list.
+fn ask_list(
+    prompt: &str,
+    choices: &[(String, String)],
+    preselected: impl Fn(&str) -> bool,
+    current: &[String],
+    theme: &ColorfulTheme,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut selected = Vec::new();
+    if !choices.is_empty() {
+        let labels: Vec<&str> = choices.iter().map(|(_, label)| label.as_str()).collect();
+        let defaults: Vec<bool> = choices.iter().map(|(value, _)| preselected(value)).collect();
+        let indices = MultiSelect::with_theme(theme)
+            .with_prompt(format!("{} (space to toggle, enter to confirm)", prompt))
+            .items(&labels)
+            .defaults(&defaults)
+            .interact()?;
+        selected.extend(indices.into_iter().map(|i| choices[i].0.clone()));
+    }
+
+    let extra: Vec<&String> = current.iter().filter(|value| !choices.iter().any(|(v, _)| v == *value)).collect();
+    let extra = extra.iter().map(|value| value.as_str()).collect::<Vec<_>>().join(",");
+    let additional: String = Input::with_theme(theme)
+        .with_prompt(format!("{}, additional (comma-separated)", prompt))
+        .with_initial_text(extra)
+        .allow_empty(true)
+        .interact_text()?;
+    selected.extend(
+        additional
+            .split(',')
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty() && !selected.contains(value))
+            .collect::<Vec<_>>(),
+    );
+    Ok(selected)
+}
+
+/// Counts the files per extension in the project, most common first. Known deny directories
+/// are not descended into.
+fn detect_extensions(project_dir: &Path) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut pending = vec![project_dir.to_path_buf()];
+    let mut seen = 0;
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            seen += 1;
+            if seen > DETECTION_LIMIT {
+                break;
+            }
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if path.is_dir() {
+                if !KNOWN_DENY_DIRS.contains(&name.as_str()) {
+                    pending.push(path);
+                }
+            } else if let Some(extension) = path.extension() {
+                *counts.entry(extension.to_string_lossy().into_owned()).or_default() += 1;
+            }
+        }
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// Lists the well-known build, dependency and tooling directories present at the top of the project.
+fn detect_deny_dirs(project_dir: &Path) -> Vec<String> {
+    KNOWN_DENY_DIRS
+        .iter()
+        .filter(|dir| project_dir.join(dir).is_dir())
+        .map(|dir| dir.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_detect() {
+        let project_dir = TempDir::new("detect");
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::create_dir_all(project_dir.join("target/debug")).unwrap();
+        fs::write(project_dir.join("Cargo.toml"), "").unwrap();
+        fs::write(project_dir.join("src/main.rs"), "").unwrap();
+        fs::write(project_dir.join("src/lib.rs"), "").unwrap();
+        fs::write(project_dir.join("target/debug/build.rs"), "").unwrap();
+
+        assert_eq!(detect_extensions(&project_dir), vec![("rs".to_string(), 2), ("toml".to_string(), 1)]);
+        assert_eq!(detect_deny_dirs(&project_dir), vec!["target"]);
+        let config = detected_defaults(&project_dir);
+        assert_eq!(config.allowed_extensions, vec!["rs", "toml"]);
+        assert_eq!(config.deny_dirs, vec!["target"]);
+    }
+
+    #[test]
+    fn test_answered_output_dir() {
+        let project_dir = TempDir::new("answered-output-dir");
+        let config = Config {
+            project_name: "app".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(answered_output_dir(&config, &project_dir, "prompts/{project_name}"), project_dir.join("prompts/app"));
+        let absolute = project_dir.join("elsewhere");
+        assert_eq!(answered_output_dir(&config, Path::new("unrelated"), &absolute.display().to_string()), absolute);
+        assert_eq!(answered_output_dir(&config, &project_dir, ""), project_dir.join(DEFAULT_OUTPUT_DIR));
+    }
+}
@@ -0,0 +1,284 @@
+// src/json.rs
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::anonymize::Anonymizer;
+use crate::config::Config;
+use crate::{fence_language, images, read_cleaned_source, Context};
+
+/// A prompt as a JSON document, for tools that post-process prompts.
+#[derive(Debug, Serialize)]
+struct Document<'a> {
+    intro: String,
+    tree: String,
+    goal: String,
+    /// The closing section of the prompt, `outro_prompt`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outro: Option<String>,
+    files: Vec<File<'a>>,
+    /// The images listed with `--with-images`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    images: Vec<ImageEntry>,
+    /// Whether the walk timed out and some files are missing.
+    partial: bool,
+}
+
+/// One image listed with `--with-images`.
+#[derive(Debug, Serialize)]
+struct ImageEntry {
+    path: String,
+    width: Option<u32>,
+    height: Option<u32>,
+    /// Size of the file, in bytes.
+    size: u64,
+}
+
+/// One included file, cleaned like in text prompts.
+#[derive(Debug, Serialize)]
+struct File<'a> {
+    path: String,
+    /// The language of the file as tagged in markdown fences, e.g. `rust`.
+    language: &'a str,
+    content: String,
+    tokens: usize,
+}
+
+/// The body of a chat completions request, without the model.
+#[derive(Debug, Serialize)]
+struct ChatRequest {
+    messages: Vec<Message>,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    role: &'static str,
+    content: Content,
+}
+
+/// The content of a message: text, or parts mixing text and images for multimodal models.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Content {
+    Text(String),
+    Parts(Vec<Part>),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Part {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Debug, Serialize)]
+struct ImageUrl {
+    /// The image as a `data:` URL.
+    url: String,
+}
+
+/// Writes the prompt as a JSON document with the introductory prompt, the tree, the goal and
+/// the included files as `{path, language, content, tokens}` objects, followed by the images
+/// listed with `--with-images` as `{path, width, height, size}` objects.
+///
+/// # Arguments
+/// * `json_file` - Where the document is written.
+/// * `context` - The context built for the prompt, whose files are cleaned again one by one.
+/// * `goal` - The goal as entered, anonymized with the rest of the document.
+/// * `anonymizer` - Applied to every field when `--anonymize` is given.
+pub fn write_json<W: Write>(
+    json_file: &mut W,
+    config: &Config,
+    project_dir: &Path,
+    context: &Context,
+    goal: &str,
+    anonymizer: Option<&Anonymizer>,
+) -> io::Result<()> {
+    let anonymize = |text: &str| match anonymizer {
+        Some(anonymizer) => anonymizer.apply(text).0,
+        None => text.to_string(),
+    };
+    let mut files = Vec::with_capacity(context.files.len());
+    for file in &context.files {
+        let content = anonymize(&read_cleaned_source(config, project_dir, &file.source)?);
+        files.push(File {
+            path: anonymize(&file.path),
+            language: fence_language(&file.path),
+            tokens: config.tokenizer.count(&content),
+            content,
+        });
+    }
+    let document = Document {
+        intro: anonymize(&config.intro_prompt),
+        tree: anonymize(&context.trees),
+        goal: anonymize(goal),
+        outro: context.outro_prompt.as_deref().map(anonymize),
+        files,
+        images: context
+            .images
+            .iter()
+            .map(|image| ImageEntry {
+                path: anonymize(&image.path),
+                width: image.dimensions.map(|(width, _)| width),
+                height: image.dimensions.map(|(_, height)| height),
+                size: image.size,
+            })
+            .collect(),
+        partial: context.timed_out,
+    };
+    serde_json::to_writer_pretty(&mut *json_file, &document)?;
+    writeln!(json_file)
+}
+
+/// Writes the prompt as the `messages` array of a chat completions request: the introductory
+/// prompt as the system message, and the tree, the files and the goal as the user message.
+/// Add a `model` field to POST it as is.
+///
+/// # Arguments
+/// * `json_file` - Where the request body is written.
+/// * `context` - The context built for the prompt, already anonymized with `--anonymize`.
+/// * `goal` - The goal, already anonymized with `--anonymize`.
+/// * `timeout` - The `--timeout` value, mentioned in the user message when the prompt is partial.
+/// * `anonymizer` - Applied to the introductory prompt when `--anonymize` is given.
+/// * `embed_images` - Whether the images of the context are sent as image parts after the text
+///   of the user message, up to [`images::MAX_EMBEDDED_BYTES`] each.
+pub fn write_messages<W: Write>(
+    json_file: &mut W,
+    config: &Config,
+    context: &Context,
+    goal: &str,
+    timeout: Option<Duration>,
+    anonymizer: Option<&Anonymizer>,
+    embed_images: bool,
+) -> io::Result<()> {
+    let intro = match anonymizer {
+        Some(anonymizer) => anonymizer.apply(&config.intro_prompt).0,
+        None => config.intro_prompt.clone(),
+    };
+    let strings = context.strings;
+    let mut user = String::new();
+    if let (true, Some(timeout)) = (context.timed_out, timeout) {
+        user.push_str(&format!("{}\n", strings.partial_notice(timeout)));
+    }
+    // The context starts with the introductory prompt, sent as the system message instead
+    let content = String::from_utf8_lossy(&context.content);
+    user.push_str(content.strip_prefix(&format!("{}\n", intro)).unwrap_or(&content));
+    user.push_str(&format!("{}: {}\n", strings.specific_goal, goal));
+    if context.answer_with_diff {
+        user.push_str(&format!("{}\n", strings.diff_instruction));
+    }
+    if context.answer_in_language {
+        user.push_str(&format!("{}\n", strings.answer_instruction));
+    }
+    if let Some(outro_prompt) = &context.outro_prompt {
+        user.push_str(&format!("{}\n", outro_prompt.trim_end()));
+    }
+    let mut image_parts = Vec::new();
+    for image in context.images.iter().filter(|_| embed_images) {
+        if image.size > images::MAX_EMBEDDED_BYTES {
+            eprintln!("Warning: {} is not embedded, it is larger than {} kB.", image.path, images::MAX_EMBEDDED_BYTES / 1024);
+            continue;
+        }
+        if let Some(url) = image.data_url()? {
+            image_parts.push(Part::ImageUrl { image_url: ImageUrl { url } });
+        }
+    }
+    let user = if image_parts.is_empty() {
+        Content::Text(user)
+    } else {
+        Content::Parts([Part::Text { text: user }].into_iter().chain(image_parts).collect())
+    };
+    let request = ChatRequest {
+        messages: vec![
+            Message { role: "system", content: Content::Text(intro) },
+            Message { role: "user", content: user },
+        ],
+    };
+    serde_json::to_writer_pretty(&mut *json_file, &request)?;
+    writeln!(json_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::testing::TempDir;
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::manifest::IncludedFile;
+
+    #[test]
+    fn test_write_json() {
+        let project_dir = TempDir::new("json");
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::write(project_dir.join("src/main.rs"), "// Entry point\nfn main() {}\n").unwrap();
+        let config = Config {
+            intro_prompt: "Intro".to_string(),
+            ..Default::default()
+        };
+        let context = Context {
+            content: Vec::new(),
+            files: vec![IncludedFile {
+                path: "src/main.rs".to_string(),
+                source: project_dir.join("src/main.rs"),
+            }],
+            trees: "└── src\n    └── main.rs\n".to_string(),
+            strings: config.strings(),
+            answer_in_language: false,
+            answer_with_diff: false,
+            outro_prompt: None,
+            timed_out: false,
+            exclusions: None,
+            images: Vec::new(),
+            redactions: Vec::new(),
+            warnings: Vec::new(),
+        };
+
+        let mut messages = Vec::new();
+        let context = Context {
+            content: b"Intro\nFile: src/main.rs\n".to_vec(),
+            ..context
+        };
+        write_messages(&mut messages, &config, &context, "Add a flag", None, None, false).unwrap();
+        let request: Value = serde_json::from_slice(&messages).unwrap();
+        assert_eq!(
+            request,
+            serde_json::json!({ "messages": [
+                { "role": "system", "content": "Intro" },
+                { "role": "user", "content": "File: src/main.rs\nSpecific Goal: Add a flag\n" },
+            ] })
+        );
+
+        let mut json = Vec::new();
+        write_json(&mut json, &config, &project_dir, &context, "Add a flag", None).unwrap();
+        let document: Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(document["intro"], "Intro");
+        assert_eq!(document["tree"], "└── src\n    └── main.rs\n");
+        assert_eq!(document["goal"], "Add a flag");
+        assert_eq!(document["partial"], false);
+        assert_eq!(
+            document["files"][0],
+            serde_json::json!({ "path": "src/main.rs", "language": "rust", "content": "fn main() {}", "tokens": config.tokenizer.count("fn main() {}") })
+        );
+
+        // Images follow the text of the user message as data URLs
+        fs::write(project_dir.join("logo.gif"), b"GIF89a\x01\x00\x01\x00").unwrap();
+        let image = images::Image::read("logo.gif".to_string(), &project_dir.join("logo.gif")).unwrap();
+        assert_eq!(image.dimensions, Some((1, 1)));
+        let context = Context { images: vec![image], ..context };
+        let mut messages = Vec::new();
+        write_messages(&mut messages, &config, &context, "Add a flag", None, None, true).unwrap();
+        let request: Value = serde_json::from_slice(&messages).unwrap();
+        assert_eq!(
+            request["messages"][1]["content"],
+            serde_json::json!([
+                { "type": "text", "text": "File: src/main.rs\nSpecific Goal: Add a flag\n" },
+                { "type": "image_url", "image_url": { "url": "data:image/gif;base64,R0lGODlhAQABAA==" } },
+            ])
+        );
+    }
+}
@@ -0,0 +1,84 @@
+// src/manifest.rs
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// A file whose content was included in a prompt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IncludedFile {
+    /// Path as shown in the prompt.
+    pub path: String,
+    /// Location of the file on disk.
+    pub source: PathBuf,
+}
+
+/// Describes a generated prompt, saved next to it as `<prompt>.manifest.json` so that
+/// later commands (such as `followup`) can tell what the model was shown.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub goal: String,
+    pub files: Vec<IncludedFile>,
+}
+
+/// Returns the manifest path of a prompt file: `name.txt` becomes `name.manifest.json`.
+pub fn manifest_path(prompt_path: &Path) -> PathBuf {
+    prompt_path.with_extension("manifest.json")
+}
+
+pub fn save_manifest(manifest: &Manifest, prompt_path: &Path) -> io::Result<()> {
+    let content = serde_json::to_string_pretty(manifest)?;
+    fs::write(manifest_path(prompt_path), content)
+}
+
+/// Loads the most recently written manifest of the project from its output directory.
+pub fn load_latest_manifest(output_dir: &Path, project_name: &str) -> Result<Manifest, Box<dyn std::error::Error>> {
+    let prefix = format!("{}_", project_name);
+    let latest = fs::read_dir(output_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.starts_with(&prefix) && name.ends_with(".manifest.json")
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+        .ok_or_else(|| format!("No prompt manifest found for {} in {}", project_name, output_dir.display()))?;
+    Ok(serde_json::from_str(&fs::read_to_string(latest)?)?)
+}
+
+/// Returns the files of the manifest that `response` refers to, by path or by file name.
+pub fn mentioned_files<'a>(manifest: &'a Manifest, response: &str) -> Vec<&'a IncludedFile> {
+    manifest
+        .files
+        .iter()
+        .filter(|file| {
+            let file_name = Path::new(&file.path).file_name().map(|name| name.to_string_lossy());
+            response.contains(&file.path) || file_name.is_some_and(|name| response.contains(name.as_ref()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mentioned_files() {
+        let file = |path: &str| IncludedFile {
+            path: path.to_string(),
+            source: PathBuf::from("/project").join(path),
+        };
+        let manifest = Manifest {
+            goal: "Fix the parser".to_string(),
+            files: vec![file("src/parser.rs"), file("src/lexer.rs"), file("src/main.rs")],
+        };
+        let response = "--- a/src/parser.rs\n+++ b/src/parser.rs\n...\nYou may also want to update lexer.rs.";
+
+        let mentioned: Vec<&str> = mentioned_files(&manifest, response).iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(mentioned, vec!["src/parser.rs", "src/lexer.rs"]);
+        assert_eq!(manifest_path(Path::new("/out/project_20240101.txt")), Path::new("/out/project_20240101.manifest.json"));
+    }
+}
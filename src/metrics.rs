@@ -0,0 +1,71 @@
+// src/metrics.rs
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde::Serialize;
+
+/// Measurements of one prompt generation, exported with `--metrics-file`.
+#[derive(Debug, Serialize)]
+pub struct Metrics {
+    pub project: String,
+    /// Local time the prompt was generated, in RFC 3339 format.
+    pub timestamp: String,
+    /// Time spent walking, cleaning and writing, excluding the time spent typing the goal.
+    pub duration_ms: u128,
+    /// Number of files whose content was included.
+    pub files: usize,
+    /// Estimated number of tokens of the whole prompt.
+    pub tokens: usize,
+    /// Whether generation was cut short by `--timeout`.
+    pub partial: bool,
+    /// Output format: `text`, `bundle`, `json` or `messages`.
+    pub format: String,
+    /// Token counts found in the cache of earlier runs, out of `token_cache_lookups`.
+    pub token_cache_hits: usize,
+    /// Token counts looked up in the cache; only long texts counted with a BPE encoding are.
+    pub token_cache_lookups: usize,
+}
+
+/// Appends `metrics` to `path` as a single JSON line, so that successive runs accumulate in
+/// the same file and can be ingested as JSON Lines.
+pub fn append_metrics(metrics: &Metrics, path: &Path) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(metrics)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_append_metrics() {
+        let dir = TempDir::new("metrics");
+        let path = dir.join("metrics.jsonl");
+        let metrics = |files| Metrics {
+            project: "app".to_string(),
+            timestamp: "2024-01-01T12:00:00+00:00".to_string(),
+            duration_ms: 42,
+            files,
+            tokens: 1200,
+            partial: false,
+            format: "text".to_string(),
+            token_cache_hits: 2,
+            token_cache_lookups: 3,
+        };
+
+        append_metrics(&metrics(3), &path).unwrap();
+        append_metrics(&metrics(4), &path).unwrap();
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["files"], 3);
+        assert_eq!(lines[1]["files"], 4);
+        assert_eq!(lines[1]["project"], "app");
+        assert_eq!(lines[1]["duration_ms"], 42);
+        assert_eq!(lines[1]["partial"], false);
+        assert_eq!(lines[1]["token_cache_hits"], 2);
+        assert_eq!(lines[1]["token_cache_lookups"], 3);
+    }
+}
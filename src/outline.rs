@@ -0,0 +1,193 @@
+// src/outline.rs
+
+/// Placeholder of each run of lines left out of an outline.
+const ELIDED: &str = "...";
+
+/// Keywords of the declarations whose block holds more declarations, such as the methods of a
+/// class, in brace languages.
+const CONTAINERS: &[&str] = &["class", "interface", "struct", "enum", "union", "trait", "impl", "namespace", "object", "mod", "extern"];
+
+/// Returns the outline of `content`: its declarations without their bodies, found line by line
+/// for the languages without a parser. Each run of lines left out becomes a `...` line.
+///
+/// In Python and Ruby, unindented lines are kept, and so are `def`, `class` and `module` lines
+/// and decorators at any depth, with the docstring following a Python declaration. In brace
+/// languages, the lines outside any block are kept, and so are the lines of the blocks opened
+/// by a container declaration (`class`, `interface`, `struct`, `impl`, ...), such as the
+/// method signatures of a class.
+///
+/// # Arguments
+/// * `content` - The file content, preferably with only its doc comments left.
+/// * `extension` - The file extension indicating the programming language (e.g., "py", "ts", "go").
+///
+/// # Returns
+/// The outline, or `None` when the language is not supported.
+pub fn outline(content: &str, extension: &str) -> Option<String> {
+    let keep = match extension {
+        "py" => indented_lines(content, &["def", "async def", "class"], true),
+        "rb" => indented_lines(content, &["def", "class", "module"], false),
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "go" | "java" | "c" | "h" | "cpp" | "cc" | "cxx" | "hpp" | "hh" | "cs" | "kt" | "swift" | "php" | "scala"
+        | "rs" => brace_lines(content),
+        _ => return None,
+    };
+
+    let mut outline = Vec::new();
+    let mut elided = false;
+    for (line, kept) in content.lines().zip(keep) {
+        if kept || line.trim().is_empty() {
+            outline.push(line.to_string());
+            elided = false;
+        } else if !elided {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            outline.push(format!("{}{}", indent, ELIDED));
+            elided = true;
+        }
+    }
+    Some(outline.join("\n"))
+}
+
+/// Returns which lines of an indentation-based language are kept: the unindented ones and the
+/// declarations starting with one of `keywords` or a decorator, with the docstring following
+/// a declaration when `docstrings` is set.
+fn indented_lines(content: &str, keywords: &[&str], docstrings: bool) -> Vec<bool> {
+    let mut keep = Vec::new();
+    let mut after_declaration = false;
+    let mut docstring_end: Option<&str> = None;
+    for line in content.lines() {
+        let item = line.trim_start();
+        if let Some(quotes) = docstring_end {
+            keep.push(true);
+            if item.contains(quotes) {
+                docstring_end = None;
+            }
+            continue;
+        }
+        let is_declaration = item.starts_with('@')
+            || keywords.iter().any(|keyword| item.strip_prefix(keyword).is_some_and(|rest| rest.starts_with([' ', '(', ':'])));
+        let quotes = ["\"\"\"", "'''"].into_iter().find(|quotes| item.starts_with(quotes));
+        match quotes {
+            Some(quotes) if docstrings && (after_declaration || keep.is_empty()) => {
+                keep.push(true);
+                if !item[quotes.len()..].contains(quotes) {
+                    docstring_end = Some(quotes);
+                }
+            }
+            _ => keep.push(line == item || is_declaration),
+        }
+        if !item.is_empty() {
+            after_declaration = is_declaration && !item.starts_with('@');
+        }
+    }
+    keep
+}
+
+/// Returns which lines of a brace language are kept: those outside any block, or only inside
+/// blocks opened by a container declaration. A line starting with `}` is judged after the
+/// blocks it closes.
+fn brace_lines(content: &str) -> Vec<bool> {
+    // Whether each open block belongs to a container declaration
+    let mut blocks: Vec<bool> = Vec::new();
+    // The statement read since the last brace or semicolon
+    let mut statement = String::new();
+    let mut keep = Vec::new();
+    for line in content.lines() {
+        let item = line.trim_start();
+        let closing = item.chars().take_while(|&c| c == '}').count();
+        for _ in 0..closing {
+            blocks.pop();
+        }
+        keep.push(blocks.iter().all(|&container| container));
+        // Doc comments may hold braces of their own
+        if item.starts_with("//") || item.starts_with("/*") || item.starts_with('*') {
+            continue;
+        }
+        let mut quote = None;
+        let mut escaped = false;
+        for c in item[closing..].chars() {
+            match (quote, c) {
+                (Some(_), _) if escaped => escaped = false,
+                (Some(_), '\\') => escaped = true,
+                (Some(open), c) if c == open => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'' | '`') => quote = Some(c),
+                (None, '{') => {
+                    blocks.push(is_container(&statement));
+                    statement.clear();
+                }
+                (None, '}') => {
+                    blocks.pop();
+                    statement.clear();
+                }
+                (None, ';') => statement.clear(),
+                (None, c) => statement.push(c),
+            }
+        }
+        statement.push(' ');
+    }
+    keep
+}
+
+/// Returns whether `statement`, the text before an opening brace, declares a container.
+fn is_container(statement: &str) -> bool {
+    statement
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .any(|word| CONTAINERS.contains(&word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outline() {
+        let python = "\
+\"\"\"Shapes.\"\"\"
+import math
+
+class Circle:
+    \"\"\"A circle.
+
+    Immutable.
+    \"\"\"
+    @property
+    def area(self):
+        return math.pi * self.r ** 2
+
+def unit():
+    return Circle(1)
+";
+        assert_eq!(
+            outline(python, "py").unwrap(),
+            "\"\"\"Shapes.\"\"\"\nimport math\n\nclass Circle:\n    \"\"\"A circle.\n\n    Immutable.\n    \"\"\"\n    @property\n    def area(self):\n        ...\n\ndef unit():\n    ..."
+        );
+
+        let typescript = "\
+import { api } from './api';
+
+/** A user store. */
+export class Store {
+    private users: Map<string, User> = new Map();
+
+    load(id: string): User {
+        if (!this.users.has(id)) {
+            this.users.set(id, api.get(`/users/${id}`));
+        }
+        return this.users.get(id)!;
+    }
+}
+
+export function create(): Store {
+    return new Store();
+}
+";
+        assert_eq!(
+            outline(typescript, "ts").unwrap(),
+            "import { api } from './api';\n\n/** A user store. */\nexport class Store {\n    private users: Map<string, User> = new Map();\n\n    \
+             load(id: string): User {\n        ...\n    }\n}\n\nexport function create(): Store {\n    ...\n}"
+        );
+
+        let go = "type Point struct {\n\tX int\n}\n\nfunc (p Point) Norm() int {\n\treturn p.X\n}\n";
+        assert_eq!(outline(go, "go").unwrap(), "type Point struct {\n\tX int\n}\n\nfunc (p Point) Norm() int {\n\t...\n}");
+        assert_eq!(outline("a,b\n", "csv"), None);
+    }
+}
@@ -0,0 +1,100 @@
+// src/pattern.rs
+
+use std::path::{Component, Path};
+
+/// Returns whether `relative_path` matches `pattern`.
+///
+/// A pattern without a `/` is a bare name matching the last component of the path, wherever it
+/// is (`target`, `*.min.js`). Any other pattern is matched against the whole path, relative to
+/// the project directory (`docs/generated`, `/docs`, `**/snapshots`). Within a component, `*`
+/// matches any sequence of characters and `?` a single one; a `**` component matches any number
+/// of components, including none.
+///
+/// # Arguments
+/// * `pattern` - The pattern, using `/` as separator. A trailing `/` is ignored.
+/// * `relative_path` - The path to test, relative to the project directory.
+pub fn matches(pattern: &str, relative_path: &Path) -> bool {
+    let components: Vec<String> = relative_path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+
+    let pattern = pattern.trim_end_matches('/');
+    if !pattern.contains('/') {
+        return components.last().is_some_and(|name| matches_component(pattern, name));
+    }
+
+    let parts: Vec<&str> = pattern.split('/').filter(|part| !part.is_empty()).collect();
+    let names: Vec<&str> = components.iter().map(String::as_str).collect();
+    matches_parts(&parts, &names)
+}
+
+fn matches_parts(parts: &[&str], names: &[&str]) -> bool {
+    match parts.split_first() {
+        None => names.is_empty(),
+        Some((&"**", rest)) => (0..=names.len()).any(|skip| matches_parts(rest, &names[skip..])),
+        Some((part, rest)) => names
+            .split_first()
+            .is_some_and(|(name, names)| matches_component(part, name) && matches_parts(rest, names)),
+    }
+}
+
+/// Matches a single path component against a pattern component with `*` and `?` wildcards.
+fn matches_component(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` in the pattern and of the name character it is matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` absorb one more character
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        // Bare names match anywhere
+        assert!(matches("target", Path::new("target")));
+        assert!(matches("target", Path::new("crates/core/target")));
+        assert!(matches("*.min.js", Path::new("static/app.min.js")));
+        assert!(!matches("target", Path::new("targets")));
+
+        // Paths are anchored at the project directory
+        assert!(matches("docs/generated", Path::new("docs/generated")));
+        assert!(matches("docs/generated/", Path::new("docs/generated")));
+        assert!(matches("/docs", Path::new("docs")));
+        assert!(!matches("docs/generated", Path::new("api/docs/generated")));
+        assert!(!matches("docs/generated", Path::new("generated")));
+
+        // `**` spans any number of components
+        assert!(matches("**/snapshots", Path::new("snapshots")));
+        assert!(matches("**/snapshots", Path::new("tests/ui/snapshots")));
+        assert!(matches("src/**/fixtures", Path::new("src/a/b/fixtures")));
+        assert!(!matches("**/snapshots", Path::new("tests/snapshots/v1")));
+
+        assert!(matches("test?_*", Path::new("tests_unit")));
+        assert!(!matches("test?_*", Path::new("test_unit")));
+    }
+}
@@ -0,0 +1,400 @@
+// src/pipeline.rs
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::clean::{self, CleanOptions};
+use crate::config::{CommentEngine, Compression, Config, EmptyLines};
+use crate::exclusions::Rule;
+use crate::gitignore::IgnoreRules;
+use crate::{is_outside, outline, script_extension, secrets, signatures, stats, syntax};
+
+/// An entry met while walking a project root, as seen by the filter stage.
+pub struct Entry<'a> {
+    /// The path of the entry.
+    pub path: &'a Path,
+    /// The path relative to the project directory, or to the root outside of it, matched
+    /// against `deny_dirs`.
+    pub project_path: &'a Path,
+    pub is_dir: bool,
+    /// Whether the entry is a symbolic link.
+    pub is_symlink: bool,
+}
+
+/// The filter stage: decides whether an entry is left out of the walk, and by which rule.
+/// Filters apply in order and the first rule found wins; the walk and `prompt-gen explain` use
+/// the same filters.
+pub trait Filter {
+    /// Returns the rule leaving `entry` out, or `None` to keep it.
+    fn rule(&self, entry: &Entry) -> Option<Rule>;
+}
+
+/// Applies `filters` in order and returns the first rule leaving `entry` out.
+pub fn filter(filters: &[&dyn Filter], entry: &Entry) -> Option<Rule> {
+    filters.iter().find_map(|filter| filter.rule(entry))
+}
+
+impl<F: Filter> Filter for Option<F> {
+    fn rule(&self, entry: &Entry) -> Option<Rule> {
+        self.as_ref().and_then(|filter| filter.rule(entry))
+    }
+}
+
+/// Leaves out symbolic links resolving outside the containment directory, when there is one.
+pub struct OutsideRoot<'a>(pub Option<&'a Path>);
+
+impl Filter for OutsideRoot<'_> {
+    fn rule(&self, entry: &Entry) -> Option<Rule> {
+        (entry.is_symlink && is_outside(entry.path, self.0)).then_some(Rule::OutsideRoot)
+    }
+}
+
+impl Filter for IgnoreRules {
+    fn rule(&self, entry: &Entry) -> Option<Rule> {
+        self.is_ignored(entry.path, entry.is_dir).then_some(Rule::Gitignore)
+    }
+}
+
+/// Leaves out the directories matching `deny_dirs`.
+pub struct DenyDirs<'a>(pub &'a Config);
+
+impl Filter for DenyDirs<'_> {
+    fn rule(&self, entry: &Entry) -> Option<Rule> {
+        if !entry.is_dir {
+            return None;
+        }
+        self.0.denying_pattern(entry.project_path).map(|pattern| Rule::DenyDir(pattern.to_string()))
+    }
+}
+
+/// Leaves out the well-known build output directories, unless `deny_build_outputs` is off.
+pub struct BuildOutputs<'a>(pub &'a Config);
+
+impl Filter for BuildOutputs<'_> {
+    fn rule(&self, entry: &Entry) -> Option<Rule> {
+        if !entry.is_dir {
+            return None;
+        }
+        self.0.build_output(entry.path, entry.project_path).map(|name| Rule::BuildOutput(name.to_string()))
+    }
+}
+
+/// Leaves out the output directory, canonicalized, and the prompts written by previous runs.
+pub struct Output<'a> {
+    pub config: &'a Config,
+    pub dir: Option<&'a Path>,
+}
+
+impl Filter for Output<'_> {
+    fn rule(&self, entry: &Entry) -> Option<Rule> {
+        let is_output = if entry.is_dir {
+            self.dir.is_some() && entry.path.canonicalize().ok().as_deref() == self.dir
+        } else {
+            entry.path.file_name().is_some_and(|file_name| self.config.is_generated_prompt(&file_name.to_string_lossy()))
+        };
+        is_output.then_some(Rule::Output)
+    }
+}
+
+/// Leaves out the files whose extension is not in `allowed_extensions`, unless their name is
+/// in `allowed_filenames`. Scripts without an extension are allowed by the extension of the
+/// language of their shebang line.
+pub struct Extensions<'a>(pub &'a Config);
+
+impl Filter for Extensions<'_> {
+    fn rule(&self, entry: &Entry) -> Option<Rule> {
+        let allowed = entry.is_dir
+            || self.0.is_allowed_file(entry.path)
+            || (entry.path.extension().is_none() && script_extension(entry.path).is_some_and(|ext| self.0.is_allowed_extension(ext)));
+        (!allowed).then_some(Rule::Extension)
+    }
+}
+
+/// Marker excluding a file from the prompts when found in one of its first lines, e.g.
+/// `// prompt-gen:ignore`.
+pub const OPT_OUT_MARKER: &str = "prompt-gen:ignore";
+
+/// Number of lines at the top of a file searched for [`OPT_OUT_MARKER`].
+pub const OPT_OUT_LINES: usize = 5;
+
+/// Leaves out the files opting out with [`OPT_OUT_MARKER`] in their first lines. Files are
+/// opened, so this filter comes last.
+pub struct OptOut;
+
+impl Filter for OptOut {
+    fn rule(&self, entry: &Entry) -> Option<Rule> {
+        (!entry.is_dir && has_opt_out_marker(entry.path)).then_some(Rule::OptOut)
+    }
+}
+
+/// Returns whether one of the first [`OPT_OUT_LINES`] lines of the file at `path` has the marker.
+fn has_opt_out_marker(path: &Path) -> bool {
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    let marker = OPT_OUT_MARKER.as_bytes();
+    BufReader::new(file)
+        .split(b'\n')
+        .take(OPT_OUT_LINES)
+        .map_while(Result::ok)
+        .any(|line| line.windows(marker.len()).any(|window| window == marker))
+}
+
+/// A file going through the clean stage.
+pub struct Source<'a> {
+    pub path: &'a Path,
+    pub extension: &'a str,
+    /// The content of the file as read.
+    pub original: &'a str,
+}
+
+/// The clean stage: each step transforms the content of a file, starting from the content as
+/// read, before it is rendered.
+pub trait Clean {
+    fn clean(&self, source: &Source, content: String) -> String;
+}
+
+/// Runs `steps` in order on the content of `source`.
+pub fn clean(steps: &[&dyn Clean], source: &Source) -> String {
+    steps.iter().fold(source.original.to_string(), |content, step| step.clean(source, content))
+}
+
+impl<C: Clean> Clean for Option<C> {
+    fn clean(&self, source: &Source, content: String) -> String {
+        match self {
+            Some(step) => step.clean(source, content),
+            None => content,
+        }
+    }
+}
+
+/// Keeps only the regions delimited by `prompt-gen:begin` and `prompt-gen:end` markers, when
+/// the file has some. Runs first, while the markers are still there.
+pub struct Regions;
+
+impl Clean for Regions {
+    fn clean(&self, _source: &Source, content: String) -> String {
+        clean::select_regions(&content).unwrap_or(content)
+    }
+}
+
+/// Minimum size, in bytes, of a test fixture collapsed by [`Fixture`].
+pub const FIXTURE_MIN_BYTES: usize = 4096;
+
+/// Collapses a large test fixture or snapshot to a one-line note, recording a warning for the
+/// caller to report.
+pub struct Fixture<'a>(pub &'a RefCell<Vec<String>>);
+
+impl Clean for Fixture<'_> {
+    fn clean(&self, source: &Source, content: String) -> String {
+        self.0.borrow_mut().push(format!(
+            "collapsed the test fixture {} to a note, set collapse_fixtures = false to include it",
+            source.path.display()
+        ));
+        clean::omitted_note(content.lines().count())
+    }
+}
+
+/// Removes comments and blank lines, and marks the original line spans.
+pub struct Comments(pub CleanOptions);
+
+impl Clean for Comments {
+    fn clean(&self, source: &Source, content: String) -> String {
+        clean::clean_content(&content, source.extension, &self.0)
+    }
+}
+
+/// Replaces embedded data blobs with markers, recording a warning for the caller to report.
+pub struct Blobs<'a>(pub &'a RefCell<Vec<String>>);
+
+impl Clean for Blobs<'_> {
+    fn clean(&self, source: &Source, content: String) -> String {
+        let (content, blobs) = clean::omit_blobs(&content);
+        if blobs > 0 {
+            self.0.borrow_mut().push(format!("omitted {} high-entropy data blob(s) from {}", blobs, source.path.display()));
+        }
+        content
+    }
+}
+
+/// Reduces a file to its declarations for its `compression_by_extension` level: Rust files to
+/// their signatures, or any supported language to its outline. Runs on the content as read, so
+/// that doc comments survive the removal of comments; the blank lines are handled as configured.
+pub struct Compress(pub Compression, pub EmptyLines);
+
+impl Clean for Compress {
+    fn clean(&self, source: &Source, content: String) -> String {
+        let options = |empty_lines| CleanOptions {
+            strip_comments: true,
+            keep_doc_comments: true,
+            engine: CommentEngine::Stream,
+            empty_lines,
+            annotate_spans: false,
+        };
+        let reduced = match self.0 {
+            Compression::Full => None,
+            Compression::Signatures if source.extension == "rs" => {
+                signatures::signatures(source.original).map(|reduced| clean::clean_content(&reduced, source.extension, &options(self.1)))
+            }
+            Compression::Signatures => None,
+            Compression::Outline => {
+                let documented = clean::clean_content(source.original, source.extension, &options(EmptyLines::Keep));
+                outline::outline(&documented, source.extension).map(|reduced| clean::handle_empty_lines(&reduced, self.1))
+            }
+        };
+        reduced.unwrap_or(content)
+    }
+}
+
+/// Reduces a file matching `reference_globs` to its declarations, when the language is supported.
+pub struct Skeleton;
+
+impl Clean for Skeleton {
+    fn clean(&self, source: &Source, content: String) -> String {
+        stats::skeleton(&clean::remove_comments(source.original, source.extension), source.extension).unwrap_or(content)
+    }
+}
+
+/// Replaces credentials with `[REDACTED]`, recording the kind of each one found so that the
+/// caller can report them.
+pub struct Secrets<'a>(pub &'a RefCell<Vec<secrets::Kind>>);
+
+impl Clean for Secrets<'_> {
+    fn clean(&self, _source: &Source, content: String) -> String {
+        let (content, found) = secrets::redact(&content);
+        self.0.borrow_mut().extend(found);
+        content
+    }
+}
+
+/// Credentials replaced by the [`Secrets`] stage in a file of the prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redaction {
+    /// The file as shown in the prompt, or `the git diff`.
+    pub path: String,
+    /// The kind of each secret replaced, in order.
+    pub kinds: Vec<secrets::Kind>,
+}
+
+impl fmt::Display for Redaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut kinds = self.kinds.clone();
+        kinds.sort();
+        kinds.dedup();
+        let kinds: Vec<String> = kinds.iter().map(ToString::to_string).collect();
+        write!(f, "redacted {} secret(s) from {}: {}", self.kinds.len(), self.path, kinds.join(", "))
+    }
+}
+
+/// Records a warning for the caller to report when the previous steps left the content with
+/// unbalanced brackets or fences.
+pub struct SyntaxCheck<'a>(pub &'a RefCell<Vec<String>>);
+
+impl Clean for SyntaxCheck<'_> {
+    fn clean(&self, source: &Source, content: String) -> String {
+        if let Some(problem) = syntax::broken_by_cleaning(source.original, &content, source.extension) {
+            self.0.borrow_mut().push(format!("{} may be broken by the cleaning: {}", source.path.display(), problem));
+        }
+        content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_pipeline() {
+        let config = Config {
+            allowed_extensions: vec!["rs".to_string()],
+            deny_dirs: vec!["generated".to_string()],
+            project_name: "app".to_string(),
+            ..Default::default()
+        };
+        let filters: [&dyn Filter; 5] = [
+            &OutsideRoot(None),
+            &DenyDirs(&config),
+            &BuildOutputs(&config),
+            &Output { config: &config, dir: None },
+            &Extensions(&config),
+        ];
+        let entry = |path: &'static str, is_dir: bool| Entry {
+            path: Path::new(path),
+            project_path: Path::new(path),
+            is_dir,
+            is_symlink: false,
+        };
+        assert_eq!(filter(&filters, &entry("src/main.rs", false)), None);
+        assert_eq!(filter(&filters, &entry("src", true)), None);
+        assert_eq!(filter(&filters, &entry("src/generated", true)), Some(Rule::DenyDir("generated".to_string())));
+        assert_eq!(filter(&filters, &entry("web/node_modules", true)), Some(Rule::BuildOutput("node_modules".to_string())));
+        assert_eq!(filter(&filters, &entry("README.md", false)), Some(Rule::Extension));
+        assert_eq!(filter(&filters, &entry("app_20240101.txt", false)), Some(Rule::Output));
+
+        let source = Source {
+            path: Path::new("src/main.rs"),
+            extension: "rs",
+            original: "// Entry point\nfn main() {}\n",
+        };
+        let comments = Comments(CleanOptions {
+            strip_comments: true,
+            keep_doc_comments: false,
+            engine: CommentEngine::Stream,
+            empty_lines: EmptyLines::Strip,
+            annotate_spans: false,
+        });
+        let warnings = RefCell::new(Vec::new());
+        assert_eq!(clean(&[&comments, &Blobs(&warnings), &None::<Skeleton>], &source), "fn main() {}");
+        assert!(warnings.borrow().is_empty());
+        assert_eq!(clean(&[&Fixture(&warnings), &comments], &source), "[... 2 lines omitted ...]");
+        assert_eq!(
+            warnings.take(),
+            vec!["collapsed the test fixture src/main.rs to a note, set collapse_fixtures = false to include it".to_string()]
+        );
+        assert_eq!(clean(&[], &source), source.original);
+
+        let source = Source {
+            path: Path::new("config.json"),
+            extension: "json",
+            original: "{\"debug\": true}\n",
+        };
+        assert_eq!(SyntaxCheck(&warnings).clean(&source, "{\"debug\":".to_string()), "{\"debug\":");
+        assert_eq!(warnings.take(), vec!["config.json may be broken by the cleaning: `{` from line 1 is never closed".to_string()]);
+
+        let blob = format!("const LOGO: &str = \"{}\";", "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg+/Zx9Qw3Lm");
+        let source = Source {
+            path: Path::new("src/logo.rs"),
+            extension: "rs",
+            original: &blob,
+        };
+        assert_eq!(clean(&[&Blobs(&warnings)], &source), "const LOGO: &str = \"[binary data omitted: 104 bytes]\";");
+        assert_eq!(warnings.into_inner(), vec!["omitted 1 high-entropy data blob(s) from src/logo.rs".to_string()]);
+    }
+
+    #[test]
+    fn test_opt_out() {
+        let dir = TempDir::new("opt-out");
+        let entry = |path: &'static str| {
+            let path = dir.join(path);
+            OptOut.rule(&Entry {
+                path: &path,
+                project_path: Path::new(""),
+                is_dir: false,
+                is_symlink: false,
+            })
+        };
+
+        // The marker is searched for in the first five lines only
+        dir.write("line5.rs", "#![allow(dead_code)]\n\n\n\n// prompt-gen:ignore\nconst KEY: &str = \"\";\n");
+        dir.write("line6.rs", "#![allow(dead_code)]\n\n\n\n\n// prompt-gen:ignore\nconst KEY: &str = \"\";\n");
+        dir.write("hash.py", "# prompt-gen:ignore\n");
+        assert_eq!(entry("line5.rs"), Some(Rule::OptOut));
+        assert_eq!(entry("line6.rs"), None);
+        assert_eq!(entry("hash.py"), Some(Rule::OptOut));
+        assert_eq!(entry("missing.rs"), None);
+    }
+}
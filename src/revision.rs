@@ -0,0 +1,123 @@
+// src/revision.rs
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Mode of the symbolic links in git trees, which are not exported.
+const SYMLINK_MODE: &str = "120000";
+
+/// The files of the project directory as they were at a git revision, exported to a temporary
+/// directory for `--at`. The directory is removed when the snapshot is dropped.
+pub struct Snapshot {
+    /// The exported project directory, named `<project>@<rev>` so that the prompt tree tells
+    /// which revision it shows.
+    pub dir: PathBuf,
+    /// The temporary directory holding `dir`.
+    parent: PathBuf,
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.parent);
+    }
+}
+
+/// Exports the files of `project_dir` at the git revision `rev` (a commit, branch or tag),
+/// read from the git objects without checking anything out.
+///
+/// Only the part of the repository under `project_dir` is exported. Symbolic links and
+/// submodules are left out.
+pub fn export(project_dir: &Path, rev: &str) -> Result<Snapshot, Box<dyn std::error::Error>> {
+    let tree = git(project_dir, &["ls-tree", "-r", "-z", "--end-of-options", rev])?;
+    let blobs: Vec<(String, String)> = String::from_utf8(tree)?
+        .split('\0')
+        .filter_map(|entry| {
+            let (info, path) = entry.split_once('\t')?;
+            let mut fields = info.split(' ');
+            let (mode, kind, object) = (fields.next()?, fields.next()?, fields.next()?);
+            (kind == "blob" && mode != SYMLINK_MODE).then(|| (object.to_string(), path.to_string()))
+        })
+        .collect();
+
+    let project_name = project_dir.file_name().map_or_else(|| "project".into(), |name| name.to_string_lossy());
+    let parent = std::env::temp_dir().join(format!("prompt-gen-at-{}", std::process::id()));
+    let snapshot = Snapshot {
+        dir: parent.join(format!("{}@{}", project_name, rev.replace(['/', '\\'], "-"))),
+        parent,
+    };
+    fs::create_dir_all(&snapshot.dir)?;
+
+    // Read all the blobs with a single git process
+    let mut cat_file = Command::new("git")
+        .arg("-C")
+        .arg(project_dir)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = cat_file.stdin.take().ok_or("git cat-file has no stdin")?;
+    let objects: String = blobs.iter().map(|(object, _)| format!("{}\n", object)).collect();
+    let writer = thread::spawn(move || stdin.write_all(objects.as_bytes()));
+    let mut output = BufReader::new(cat_file.stdout.take().ok_or("git cat-file has no stdout")?);
+    for (_, path) in &blobs {
+        let mut header = String::new();
+        output.read_line(&mut header)?;
+        let size: usize = header.split(' ').nth(2).and_then(|size| size.trim().parse().ok()).ok_or_else(|| format!("unexpected git cat-file output: {}", header.trim()))?;
+        let mut content = vec![0; size + 1];
+        output.read_exact(&mut content)?;
+        content.pop();
+
+        let file_path = snapshot.dir.join(path);
+        if let Some(dir) = file_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(file_path, content)?;
+    }
+    writer.join().map_err(|_| "failed to write to git cat-file")??;
+    cat_file.wait()?;
+    Ok(snapshot)
+}
+
+/// Runs git in `dir` and returns its raw output, or its error message when it fails.
+fn git(dir: &Path, args: &[&str]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_export() {
+        let dir = TempDir::new("export");
+        fs::create_dir_all(dir.join("app/src")).unwrap();
+        let git = |args: &[&str]| assert!(Command::new("git").arg("-C").arg(&*dir).args(args).output().unwrap().status.success());
+        git(&["init", "-q"]);
+        fs::write(dir.join("app/src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("README.md"), "# Repo\n").unwrap();
+        git(&["add", "."]);
+        git(&["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "-q", "-m", "initial"]);
+        git(&["tag", "v1"]);
+        fs::write(dir.join("app/src/main.rs"), "fn main() { run() }\n").unwrap();
+        fs::write(dir.join("app/src/new.rs"), "").unwrap();
+
+        // Only the project directory is exported, as committed
+        let snapshot = export(&dir.join("app"), "v1").unwrap();
+        assert!(snapshot.dir.ends_with("app@v1"));
+        assert_eq!(fs::read_to_string(snapshot.dir.join("src/main.rs")).unwrap(), "fn main() {}\n");
+        assert!(!snapshot.dir.join("src/new.rs").exists());
+        assert!(!snapshot.dir.join("README.md").exists());
+        let exported = snapshot.dir.clone();
+        drop(snapshot);
+        assert!(!exported.exists());
+        assert!(export(&dir, "no-such-rev").is_err());
+    }
+}
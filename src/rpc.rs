@@ -0,0 +1,344 @@
+// src/rpc.rs
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::atomic::AtomicFile;
+use crate::config::{self, Config};
+use crate::generate::Generation;
+use crate::manifest::{save_manifest, IncludedFile, Manifest};
+use crate::{cli, prompt_path, tokens, write_prompt, BuildOptions};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const CONFIG_NOT_FOUND: i64 = -32000;
+
+/// A JSON-RPC 2.0 request. Requests without an `id` are notifications and get no response.
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct GenerateParams {
+    goal: String,
+    #[serde(default)]
+    timeout: Option<String>,
+    #[serde(default)]
+    profile: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DryRunParams {
+    #[serde(default)]
+    timeout: Option<String>,
+    #[serde(default)]
+    profile: Option<String>,
+}
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError { code, message: message.into() }
+    }
+}
+
+/// Serves JSON-RPC requests for a project, one JSON document per line, until the input is closed.
+/// The project configuration is loaded once and reused by every call; when it cannot be loaded,
+/// every call fails with the reason.
+pub struct Server<'a> {
+    project_dir: &'a Path,
+    config: Result<Config, String>,
+}
+
+impl<'a> Server<'a> {
+    pub fn new(project_dir: &'a Path) -> Self {
+        let config = match project_dir.to_str() {
+            Some(dir) => config::load_config(dir).map_err(|e| e.to_string()),
+            None => Err("the project directory is not valid UTF-8".to_string()),
+        };
+        Server { project_dir, config }
+    }
+
+    /// Reads requests from `reader` and writes one response line per request to `writer`.
+    pub fn serve<R: BufRead, W: Write>(&mut self, reader: R, mut writer: W) -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_line(&line) {
+                writeln!(writer, "{}", response)?;
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles one line of input, returning the response to send, if any.
+    fn handle_line(&mut self, line: &str) -> Option<Value> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Value::Null, RpcError::new(PARSE_ERROR, e.to_string()))),
+        };
+        let request: Request = match serde_json::from_value(request) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Value::Null, RpcError::new(INVALID_REQUEST, e.to_string()))),
+        };
+
+        let result = self.dispatch(&request.method, request.params);
+        let id = request.id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, e),
+        })
+    }
+
+    fn dispatch(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "generate" => self.generate(parse_params(params)?),
+            "dryRun" => self.dry_run(parse_params(params)?),
+            "listProfiles" => self.list_profiles(),
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+        }
+    }
+
+    fn config(&self) -> Result<&Config, RpcError> {
+        self.config.as_ref().map_err(|e| {
+            RpcError::new(
+                CONFIG_NOT_FOUND,
+                format!("Cannot load the configuration of {}: {}", self.project_dir.display(), e),
+            )
+        })
+    }
+
+    /// Returns the project configuration with the settings of `profile` applied, when given.
+    fn profile_config(&self, profile: Option<&str>) -> Result<Config, RpcError> {
+        let config = self.config()?;
+        match profile {
+            Some(profile) => config.with_profile(profile).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string())),
+            None => Ok(config.clone()),
+        }
+    }
+
+    /// Writes the prompt file for `goal` and records the goal in the project history, unless
+    /// the configuration is `read_only`.
+    fn generate(&mut self, params: GenerateParams) -> Result<Value, RpcError> {
+        let timeout = parse_timeout(params.timeout.as_deref())?;
+        let config = self.profile_config(params.profile.as_deref())?;
+        let mut warnings = Vec::new();
+        let generated = Generation::new(config, self.project_dir, BuildOptions { timeout, ..Default::default() })
+            .run_with_goal(&params.goal, &mut warnings)
+            .map_err(generation_error)?;
+        let (config, context) = (&generated.config, &generated.context);
+        let prompt_path = prompt_path(config, self.project_dir).map_err(internal_error)?;
+        let mut prompt_file = AtomicFile::create(&prompt_path).map_err(internal_error)?;
+        write_prompt(&mut prompt_file, context, &generated.rendered_goal, timeout).and_then(|()| prompt_file.commit()).map_err(internal_error)?;
+        let manifest = Manifest {
+            goal: generated.goal.clone(),
+            files: context.files.clone(),
+        };
+        save_manifest(&manifest, &prompt_path).map_err(internal_error)?;
+
+        let content = String::from_utf8_lossy(&context.content);
+        let tokens = config.tokenizer.count(&content) + config.tokenizer.count(&generated.rendered_goal);
+        tokens::save_counts(&mut warnings).map_err(internal_error)?;
+        if !config.read_only {
+            let mut updated_config = self.config()?.clone();
+            updated_config.history.push(generated.goal.clone());
+            let project_dir = self.project_dir.to_string_lossy();
+            config::save_config(&updated_config, &project_dir).map_err(internal_error)?;
+            self.config = Ok(updated_config);
+        }
+
+        Ok(json!({
+            "path": prompt_path,
+            "files": file_paths(&context.files),
+            "tokens": tokens,
+            "partial": context.timed_out,
+            "warnings": warning_lines(&warnings),
+        }))
+    }
+
+    /// Walks the project like `generate` would, without writing anything.
+    fn dry_run(&self, params: DryRunParams) -> Result<Value, RpcError> {
+        let timeout = parse_timeout(params.timeout.as_deref())?;
+        let config = self.profile_config(params.profile.as_deref())?;
+        let mut warnings = Vec::new();
+        let (config, context) = Generation::new(config, self.project_dir, BuildOptions { timeout, ..Default::default() })
+            .run_without_goal(&mut warnings)
+            .map_err(generation_error)?;
+        let content = String::from_utf8_lossy(&context.content);
+        let tokens = config.tokenizer.count(&content);
+        tokens::save_counts(&mut warnings).map_err(internal_error)?;
+        Ok(json!({
+            "files": file_paths(&context.files),
+            "tokens": tokens,
+            "partial": context.timed_out,
+            "warnings": warning_lines(&warnings),
+        }))
+    }
+
+    /// Lists the `default` profile, the project configuration itself, then the named profiles.
+    fn list_profiles(&self) -> Result<Value, RpcError> {
+        let config = self.config()?;
+        let mut profiles = vec![json!({ "name": "default", "projectName": config.project_name })];
+        for name in config.profiles.keys() {
+            let profile = config.with_profile(name).map_err(internal_error)?;
+            profiles.push(json!({ "name": name, "projectName": profile.project_name }));
+        }
+        Ok(Value::Array(profiles))
+    }
+}
+
+fn file_paths(files: &[IncludedFile]) -> Vec<&str> {
+    files.iter().map(|file| file.path.as_str()).collect()
+}
+
+fn warning_lines(warnings: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(warnings).lines().map(str::to_string).collect()
+}
+
+fn parse_params<T: Default + for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    if params.is_null() {
+        return Ok(T::default());
+    }
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn parse_timeout(timeout: Option<&str>) -> Result<Option<std::time::Duration>, RpcError> {
+    timeout
+        .map(cli::parse_duration)
+        .transpose()
+        .map_err(|e| RpcError::new(INVALID_PARAMS, e))
+}
+
+/// Invalid goals and templates are the caller's error, other failures are internal.
+fn generation_error(e: io::Error) -> RpcError {
+    match e.kind() {
+        io::ErrorKind::InvalidInput => RpcError::new(INVALID_PARAMS, e.to_string()),
+        _ => internal_error(e),
+    }
+}
+
+fn internal_error(e: impl std::fmt::Display) -> RpcError {
+    RpcError::new(INTERNAL_ERROR, e.to_string())
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": error.code, "message": error.message } })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn server_without_config() -> Server<'static> {
+        Server {
+            project_dir: Path::new("/path/to/project"),
+            config: Err("Configuration not found for directory: /path/to/project".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_serve_errors() {
+        let input = concat!(
+            "not json\n",
+            "{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"unknown\"}\n",
+            "{\"jsonrpc\": \"2.0\", \"id\": 2, \"method\": \"dryRun\"}\n",
+            "{\"jsonrpc\": \"2.0\", \"id\": 3, \"method\": \"generate\", \"params\": {\"timeout\": \"1s\"}}\n",
+            "{\"jsonrpc\": \"2.0\", \"method\": \"listProfiles\"}\n",
+        );
+        let mut output = Vec::new();
+        server_without_config().serve(input.as_bytes(), &mut output).unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        // The notification gets no response
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0]["error"]["code"], PARSE_ERROR);
+        assert_eq!(responses[1]["id"], 1);
+        assert_eq!(responses[1]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[2]["error"]["code"], CONFIG_NOT_FOUND);
+        assert_eq!(
+            responses[2]["error"]["message"],
+            "Cannot load the configuration of /path/to/project: Configuration not found for directory: /path/to/project"
+        );
+        // `goal` is required
+        assert_eq!(responses[3]["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_serve_project() {
+        let dir = TempDir::new("rpc");
+        dir.write("src/main.rs", "fn main() {}\n");
+        dir.write("web/app.ts", "export {};\n");
+        let web: toml::Table = toml::from_str("project_name = \"web\"\nallowed_extensions = [\"ts\"]").unwrap();
+        let config = Config {
+            project_name: "app".to_string(),
+            intro_prompt: "Intro. {{snippet \"style\"}}".to_string(),
+            output_path: dir.join("prompts").display().to_string(),
+            allowed_extensions: vec!["rs".to_string()],
+            snippets: [("style".to_string(), "Keep it short.".to_string())].into(),
+            profiles: [("web".to_string(), web)].into(),
+            // The history is saved to the configuration file of the user otherwise
+            read_only: true,
+            ..Default::default()
+        };
+        let mut server = Server {
+            project_dir: &dir,
+            config: Ok(config),
+        };
+        let input = concat!(
+            "{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"listProfiles\"}\n",
+            "{\"jsonrpc\": \"2.0\", \"id\": 2, \"method\": \"dryRun\"}\n",
+            "{\"jsonrpc\": \"2.0\", \"id\": 3, \"method\": \"dryRun\", \"params\": {\"profile\": \"web\"}}\n",
+            "{\"jsonrpc\": \"2.0\", \"id\": 4, \"method\": \"generate\", \"params\": {\"goal\": \"Add a flag\"}}\n",
+            "{\"jsonrpc\": \"2.0\", \"id\": 5, \"method\": \"generate\", \"params\": {\"goal\": \" \"}}\n",
+        );
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses[0]["result"], json!([{ "name": "default", "projectName": "app" }, { "name": "web", "projectName": "web" }]));
+        assert_eq!(responses[1]["result"]["files"], json!(["src/main.rs"]));
+        assert_eq!(responses[1]["result"]["partial"], false);
+        assert_eq!(responses[2]["result"]["files"], json!(["web/app.ts"]));
+
+        let result = &responses[3]["result"];
+        assert_eq!(result["files"], json!(["src/main.rs"]));
+        assert_eq!(result["warnings"], json!([]));
+        let prompt = fs::read_to_string(result["path"].as_str().unwrap()).unwrap();
+        assert!(prompt.starts_with("Intro. Keep it short.\n"));
+        assert!(prompt.ends_with("Specific Goal: Add a flag\n"));
+        let goal_tokens = server.config().unwrap().tokenizer.count("Add a flag");
+        assert_eq!(result["tokens"], responses[1]["result"]["tokens"].as_u64().unwrap() + goal_tokens as u64);
+        // The empty goal is rejected like on the command line
+        assert_eq!(responses[4]["error"]["code"], INVALID_PARAMS);
+    }
+}
@@ -0,0 +1,166 @@
+// src/signatures.rs
+
+use proc_macro2::{LineColumn, Span};
+use syn::spanned::Spanned as _;
+use syn::{Attribute, Block, ImplItem, Item, Meta, TraitItem};
+
+/// Replacement of the bodies of functions and methods.
+const ELIDED_BODY: &str = "{ ... }";
+
+/// Reduces Rust source to its signatures, for architecture-level questions.
+///
+/// The bodies of functions and methods, trait default methods included, are replaced with
+/// `{ ... }`, and items under `#[cfg(test)]` are removed. Everything else is kept as written:
+/// signatures, struct and enum definitions, and comments.
+///
+/// # Arguments
+/// * `content` - The Rust source.
+///
+/// # Returns
+/// The reduced source, or `None` when `content` does not parse.
+pub fn signatures(content: &str) -> Option<String> {
+    let file = syn::parse_file(content).ok()?;
+    let mut cuts = Vec::new();
+    collect_cuts(&file.items, &mut cuts);
+
+    let line_starts: Vec<usize> = std::iter::once(0).chain(content.match_indices('\n').map(|(i, _)| i + 1)).collect();
+    let offset = |location: LineColumn| {
+        let line_start = line_starts[location.line - 1];
+        line_start + content[line_start..].chars().take(location.column).map(char::len_utf8).sum::<usize>()
+    };
+
+    let mut reduced = String::with_capacity(content.len());
+    let mut position = 0;
+    for (span, replacement) in cuts {
+        let (start, end) = (offset(span.start()), offset(span.end()));
+        // Cuts are in source order and never nested, but stay safe on overlapping spans
+        if start < position {
+            continue;
+        }
+        reduced.push_str(&content[position..start]);
+        reduced.push_str(replacement);
+        position = end;
+    }
+    reduced.push_str(&content[position..]);
+    Some(reduced)
+}
+
+/// Collects, in source order, the spans of `items` to replace: function bodies with
+/// [`ELIDED_BODY`], test items with nothing.
+fn collect_cuts(items: &[Item], cuts: &mut Vec<(Span, &'static str)>) {
+    for item in items {
+        match item {
+            _ if is_test(item_attrs(item)) => cuts.push((item.span(), "")),
+            Item::Fn(function) => cuts.push(body(&function.block)),
+            Item::Impl(implementation) => {
+                for impl_item in &implementation.items {
+                    match impl_item {
+                        ImplItem::Fn(method) if is_test(&method.attrs) => cuts.push((impl_item.span(), "")),
+                        ImplItem::Fn(method) => cuts.push(body(&method.block)),
+                        _ => {}
+                    }
+                }
+            }
+            Item::Trait(definition) => {
+                for trait_item in &definition.items {
+                    if let TraitItem::Fn(method) = trait_item {
+                        cuts.extend(method.default.as_ref().map(body));
+                    }
+                }
+            }
+            Item::Mod(module) => {
+                if let Some((_, items)) = &module.content {
+                    collect_cuts(items, cuts);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the span of a function body, braces included, with its replacement.
+fn body(block: &Block) -> (Span, &'static str) {
+    (block.span(), ELIDED_BODY)
+}
+
+/// Returns the attributes of the items that can be gated behind `#[cfg(test)]`.
+fn item_attrs(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Fn(item) => &item.attrs,
+        Item::Impl(item) => &item.attrs,
+        Item::Mod(item) => &item.attrs,
+        Item::Use(item) => &item.attrs,
+        Item::Struct(item) => &item.attrs,
+        Item::Enum(item) => &item.attrs,
+        Item::Const(item) => &item.attrs,
+        Item::Static(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+/// Returns whether `attrs` gate an item behind `#[cfg(test)]` or mark it as a `#[test]`.
+fn is_test(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| match &attr.meta {
+        Meta::Path(path) => path.is_ident("test"),
+        Meta::List(list) => list.path.is_ident("cfg") && list.tokens.to_string() == "test",
+        Meta::NameValue(_) => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signatures() {
+        let source = "\
+/// A point.
+pub struct Point { x: i32 }
+
+impl Point {
+    /// Créé à l'origine.
+    pub fn origin() -> Self {
+        Point { x: 0 }
+    }
+}
+
+pub trait Shape {
+    fn area(&self) -> f64;
+    fn name(&self) -> &str { \"shape\" }
+}
+
+fn main() {
+    // Run
+    println!(\"{}\", Point::origin().x);
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_origin() {}
+}
+";
+        assert_eq!(
+            signatures(source).unwrap(),
+            "\
+/// A point.
+pub struct Point { x: i32 }
+
+impl Point {
+    /// Créé à l'origine.
+    pub fn origin() -> Self { ... }
+}
+
+pub trait Shape {
+    fn area(&self) -> f64;
+    fn name(&self) -> &str { ... }
+}
+
+fn main() { ... }
+
+
+"
+        );
+        assert_eq!(signatures("fn broken( {"), None);
+    }
+}
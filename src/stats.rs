@@ -0,0 +1,234 @@
+// src/stats.rs
+
+use std::fmt;
+
+/// Symbol counts of a file, found by a line-based scan of its declarations.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FileStats {
+    /// Functions and methods.
+    pub functions: usize,
+    /// Structs, enums, traits, unions and classes.
+    pub types: usize,
+    /// Test functions.
+    pub tests: usize,
+}
+
+impl fmt::Display for FileStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}, {}, {}",
+            plural(self.functions, "function"),
+            plural(self.types, "type"),
+            plural(self.tests, "test")
+        )
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// Kind of a declaration found by the scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Function,
+    Type,
+}
+
+/// A function or type declared in a file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Declaration {
+    pub kind: Kind,
+    /// Keyword introducing the declaration (`fn`, `struct`, `def`, ...), or `fn` for C functions.
+    pub keyword: &'static str,
+    pub name: String,
+    /// Whether the declaration is a test function.
+    pub test: bool,
+    /// The declaring line, with its indentation and without the opening of the body.
+    pub signature: String,
+}
+
+impl fmt::Display for Declaration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.keyword, self.name)
+    }
+}
+
+/// Counts the functions, types and tests declared in `content`.
+///
+/// # Arguments
+/// * `content` - The file content, preferably with comments removed.
+/// * `extension` - The file extension indicating the programming language (e.g., "rs", "c", "py").
+///
+/// # Returns
+/// The symbol counts, or `None` when the language is not supported.
+pub fn scan(content: &str, extension: &str) -> Option<FileStats> {
+    let declarations = declarations(content, extension)?;
+    let mut stats = FileStats::default();
+    for declaration in &declarations {
+        match declaration.kind {
+            Kind::Function => stats.functions += 1,
+            Kind::Type => stats.types += 1,
+        }
+        if declaration.test {
+            stats.tests += 1;
+        }
+    }
+    Some(stats)
+}
+
+/// Lists the functions and types declared in `content`, in file order.
+///
+/// # Arguments
+/// * `content` - The file content, preferably with comments removed.
+/// * `extension` - The file extension indicating the programming language (e.g., "rs", "c", "py").
+///
+/// # Returns
+/// The declarations, or `None` when the language is not supported.
+pub fn declarations(content: &str, extension: &str) -> Option<Vec<Declaration>> {
+    match extension {
+        "rs" => Some(scan_rust(content)),
+        "py" => Some(scan_python(content)),
+        "c" | "h" => Some(scan_c(content)),
+        _ => None,
+    }
+}
+
+/// Returns the skeleton of `content`: the signatures of its functions and types, except tests,
+/// one per line.
+///
+/// # Arguments
+/// * `content` - The file content, preferably with comments removed.
+/// * `extension` - The file extension indicating the programming language (e.g., "rs", "c", "py").
+///
+/// # Returns
+/// The skeleton, or `None` when the language is not supported.
+pub fn skeleton(content: &str, extension: &str) -> Option<String> {
+    let signatures: Vec<String> = declarations(content, extension)?
+        .into_iter()
+        .filter(|declaration| !declaration.test)
+        .map(|declaration| declaration.signature)
+        .collect();
+    Some(signatures.join("\n"))
+}
+
+fn scan_rust(content: &str) -> Vec<Declaration> {
+    let mut declarations = Vec::new();
+    let mut in_test = false;
+    for source_line in content.lines() {
+        let line = source_line.trim_start();
+        if line.starts_with("#[") && line.trim_end().ends_with("test]") {
+            in_test = true;
+            continue;
+        }
+        let item = strip_prefixes(line, &["pub(crate) ", "pub(super) ", "pub ", "const ", "async ", "unsafe ", "extern \"C\" "]);
+        if let Some(rest) = item.strip_prefix("fn ") {
+            declarations.push(declaration(Kind::Function, "fn", rest, in_test, source_line));
+        } else if let Some((keyword, rest)) = ["struct", "enum", "trait", "union"]
+            .iter()
+            .find_map(|keyword| Some((*keyword, item.strip_prefix(keyword)?.strip_prefix(' ')?)))
+        {
+            declarations.push(declaration(Kind::Type, keyword, rest, false, source_line));
+        }
+        // Other attributes may sit between `#[test]` and the function
+        if !line.starts_with("#[") {
+            in_test = false;
+        }
+    }
+    declarations
+}
+
+fn scan_python(content: &str) -> Vec<Declaration> {
+    let mut declarations = Vec::new();
+    for source_line in content.lines() {
+        let item = strip_prefixes(source_line.trim_start(), &["async "]);
+        if let Some(rest) = item.strip_prefix("def ") {
+            declarations.push(declaration(Kind::Function, "def", rest, rest.starts_with("test"), source_line));
+        } else if let Some(rest) = item.strip_prefix("class ") {
+            declarations.push(declaration(Kind::Type, "class", rest, false, source_line));
+        }
+    }
+    declarations
+}
+
+/// C declarations are recognized at the start of a line: function definitions end with `)`
+/// or `) {`, struct, union and enum definitions open a brace.
+fn scan_c(content: &str) -> Vec<Declaration> {
+    let mut declarations = Vec::new();
+    let lines: Vec<&str> = content.lines().collect();
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with([' ', '\t', '#', '}']) || line.trim().is_empty() {
+            continue;
+        }
+        let item = strip_prefixes(line, &["typedef ", "static ", "inline "]);
+        let trimmed = line.trim_end();
+        let type_keyword = ["struct", "union", "enum"]
+            .iter()
+            .find_map(|keyword| Some((*keyword, item.strip_prefix(keyword)?.strip_prefix(' ')?)));
+        if let (Some((keyword, rest)), true) = (type_keyword, trimmed.ends_with('{')) {
+            declarations.push(declaration(Kind::Type, keyword, rest, false, line));
+        } else if let (Some((head, _)), false) = (trimmed.split_once('('), trimmed.ends_with(';')) {
+            let opens_body = trimmed.ends_with('{') || lines.get(i + 1).is_some_and(|next| next.trim_start().starts_with('{'));
+            if opens_body {
+                // The name is the last word before the parameters, without pointer stars
+                let name = head.split_whitespace().last().unwrap_or("").trim_start_matches('*');
+                declarations.push(declaration(Kind::Function, "fn", name, false, line));
+            }
+        }
+    }
+    declarations
+}
+
+/// Builds a declaration named by the identifier at the start of `rest`, declared on `line`.
+fn declaration(kind: Kind, keyword: &'static str, rest: &str, test: bool, line: &str) -> Declaration {
+    let name = rest.trim_start().split(|c: char| !(c.is_alphanumeric() || c == '_')).next().unwrap_or("");
+    Declaration {
+        kind,
+        keyword,
+        name: name.to_string(),
+        test,
+        signature: line.split('{').next().unwrap_or("").trim_end().trim_end_matches(':').to_string(),
+    }
+}
+
+fn strip_prefixes<'a>(mut line: &'a str, prefixes: &[&str]) -> &'a str {
+    while let Some(rest) = prefixes.iter().find_map(|prefix| line.strip_prefix(prefix)) {
+        line = rest;
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        let rust = "pub struct Config {}\nenum Mode { A }\npub(crate) async fn load() {}\nimpl Config {\n    pub fn new() -> Self {}\n}\n#[cfg(test)]\nmod tests {\n    #[test]\n    #[ignore]\n    fn test_load() {}\n}\n";
+        assert_eq!(scan(rust, "rs"), Some(FileStats { functions: 3, types: 2, tests: 1 }));
+
+        let python = "class Parser:\n    def parse(self):\n        pass\n\nasync def fetch():\n    pass\n\ndef test_parse():\n    pass\n";
+        assert_eq!(scan(python, "py"), Some(FileStats { functions: 3, types: 1, tests: 1 }));
+
+        let c = "#include <stdio.h>\nstruct point {\n    int x;\n};\nint add(int a, int b);\nstatic int add(int a, int b)\n{\n    return a + b;\n}\nint main(void) {\n    return add(1, 2);\n}\n";
+        assert_eq!(scan(c, "c"), Some(FileStats { functions: 2, types: 1, tests: 0 }));
+
+        assert_eq!(scan("anything", "txt"), None);
+
+        let names: Vec<String> = declarations(c, "c").unwrap().iter().map(|declaration| declaration.to_string()).collect();
+        assert_eq!(names, vec!["struct point", "fn add", "fn main"]);
+        let test = declarations(rust, "rs").unwrap().into_iter().find(|declaration| declaration.test).unwrap();
+        assert_eq!((test.kind, test.name.as_str()), (Kind::Function, "test_load"));
+        assert_eq!(
+            skeleton(rust, "rs").unwrap(),
+            "pub struct Config\nenum Mode\npub(crate) async fn load()\n    pub fn new() -> Self"
+        );
+        assert_eq!(skeleton(python, "py").unwrap(), "class Parser\n    def parse(self)\nasync def fetch()");
+        assert_eq!(FileStats { functions: 1, types: 0, tests: 2 }.to_string(), "1 function, 0 types, 2 tests");
+    }
+}
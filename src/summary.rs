@@ -0,0 +1,170 @@
+// src/summary.rs
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::config::Config;
+use crate::{clean, containment, project_roots, stats, visit_dirs, Walk};
+
+/// Default location of the summary, relative to the project directory.
+pub const DEFAULT_PATH: &str = "llms.txt";
+
+/// Manifests, entry points and documents listed under "Key files" when present in a root.
+const KEY_FILES: &[&str] = &[
+    "README.md", "Cargo.toml", "package.json", "pyproject.toml", "setup.py", "go.mod", "pom.xml",
+    "build.gradle", "CMakeLists.txt", "Makefile", "Dockerfile", "src/main.rs", "src/lib.rs",
+    "main.py", "__main__.py", "main.go", "src/main.c", "main.c", "src/index.ts", "index.js",
+];
+
+/// Maximum number of README lines kept in the digest.
+const README_DIGEST_LINES: usize = 20;
+
+/// Writes an `llms.txt`-style summary of the project: its name and introductory prompt, a
+/// digest of the README, the key files, the tree of files matching the allowed extensions and
+/// an index of the functions and types they declare.
+///
+/// The output only depends on the project content, so regenerating it after changes gives a
+/// diff limited to what changed.
+pub fn write_summary<W: Write>(config: &Config, project_dir: &Path, summary_file: &mut W) -> io::Result<()> {
+    writeln!(summary_file, "# {}", config.project_name)?;
+    writeln!(summary_file)?;
+    if !config.intro_prompt.trim().is_empty() {
+        for line in config.intro_prompt.trim().lines() {
+            writeln!(summary_file, "> {}", line)?;
+        }
+        writeln!(summary_file)?;
+    }
+
+    let containment = containment(project_dir);
+    let roots = project_roots(config, project_dir, Some(&containment), &mut Vec::new());
+    if let Some(digest) = roots.iter().find_map(|root| readme_digest(&root.path)) {
+        writeln!(summary_file, "## README")?;
+        writeln!(summary_file)?;
+        writeln!(summary_file, "{}", digest)?;
+        writeln!(summary_file)?;
+    }
+
+    let key_files: Vec<String> = roots
+        .iter()
+        .flat_map(|root| {
+            KEY_FILES
+                .iter()
+                .map(|name| root.path.join(name))
+                .filter(|path| path.is_file())
+                .map(|path| root.display_path(&path))
+        })
+        .collect();
+    if !key_files.is_empty() {
+        writeln!(summary_file, "## Key files")?;
+        writeln!(summary_file)?;
+        for path in &key_files {
+            writeln!(summary_file, "- [{}]({})", path, path)?;
+        }
+        writeln!(summary_file)?;
+    }
+
+    // Walk the roots for the tree only, file contents are not part of the summary
+    let mut walk = Walk {
+        config,
+        project_dir,
+        containment: Some(containment),
+        deadline: None,
+        timed_out: false,
+        entries: 0,
+        include_contents: false,
+        changeset: None,
+        included: Vec::new(),
+        exclusions: None,
+        ignore: None,
+        output_dir: config.output_dir(project_dir).canonicalize().ok(),
+        images: None,
+        redactions: Vec::new(),
+        warnings: Vec::new(),
+        listings: HashMap::new(),
+    };
+    writeln!(summary_file, "## Tree")?;
+    writeln!(summary_file)?;
+    writeln!(summary_file, "```")?;
+    for root in &roots {
+        if !root.path.is_dir() {
+            continue;
+        }
+        let mut tree = String::new();
+        // Label unconfigured roots with the project name, absolute paths differ between machines
+        writeln!(summary_file, "{}", root.label.as_deref().unwrap_or(&config.project_name))?;
+        visit_dirs(&root.path, "", root, &mut walk, &mut tree)?;
+        walk.check_entries()?;
+        write!(summary_file, "{}", tree)?;
+    }
+    writeln!(summary_file, "```")?;
+
+    let mut index = Vec::new();
+    for file in &walk.included {
+        let extension = file.source.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        let content = fs::read_to_string(&file.source)?;
+        let Some(declarations) = stats::declarations(&clean::remove_comments(&content, extension), extension) else {
+            continue;
+        };
+        let symbols: Vec<String> = declarations
+            .iter()
+            .filter(|declaration| !declaration.test)
+            .map(|declaration| format!("`{}`", declaration))
+            .collect();
+        if !symbols.is_empty() {
+            index.push(format!("- {}: {}", file.path, symbols.join(", ")));
+        }
+    }
+    if !index.is_empty() {
+        writeln!(summary_file)?;
+        writeln!(summary_file, "## Symbols")?;
+        writeln!(summary_file)?;
+        for line in &index {
+            writeln!(summary_file, "{}", line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the opening of the README in `root`: the text before its second heading, without
+/// the title, limited to a few lines.
+fn readme_digest(root: &Path) -> Option<String> {
+    let content = fs::read_to_string(root.join("README.md")).ok()?;
+    let digest: Vec<&str> = content
+        .lines()
+        .skip_while(|line| line.starts_with("# ") || line.trim().is_empty())
+        .take_while(|line| !line.starts_with('#'))
+        .take(README_DIGEST_LINES)
+        .collect();
+    let digest = digest.join("\n").trim().to_string();
+    (!digest.is_empty()).then_some(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_write_summary() {
+        let project_dir = TempDir::new("summary");
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::write(project_dir.join("README.md"), "# Demo\n\nA demo project.\n\n## Usage\n\nRun it.\n").unwrap();
+        fs::write(project_dir.join("src/main.rs"), "struct App;\n\nfn main() {}\n\n#[test]\nfn test_main() {}\n").unwrap();
+        let config = Config {
+            project_name: "demo".to_string(),
+            intro_prompt: "A demo.".to_string(),
+            allowed_extensions: vec!["rs".to_string()],
+            ..Default::default()
+        };
+
+        let mut summary = Vec::new();
+        write_summary(&config, &project_dir, &mut summary).unwrap();
+        assert_eq!(
+            String::from_utf8(summary).unwrap(),
+            "# demo\n\n> A demo.\n\n## README\n\nA demo project.\n\n## Key files\n\n- [README.md](README.md)\n- [src/main.rs](src/main.rs)\n\n\
+             ## Tree\n\n```\ndemo\n└── src\n    └── main.rs\n```\n\n## Symbols\n\n- src/main.rs: `struct App`, `fn main`\n"
+        );
+    }
+}
@@ -0,0 +1,178 @@
+// src/syntax.rs
+
+/// Extensions of the languages whose brackets are checked.
+const BRACKET_LANGUAGES: &[&str] = &[
+    "rs", "c", "h", "cc", "cpp", "hpp", "cs", "java", "kt", "scala", "swift", "go", "js", "jsx", "ts", "tsx", "php", "py", "json",
+];
+
+/// Extensions of the languages where `'` opens a string rather than a character literal.
+const SINGLE_QUOTE_STRINGS: &[&str] = &["js", "jsx", "ts", "tsx", "php", "py"];
+
+/// Returns a problem of `cleaned` that `original` does not have, such as a brace or a fence
+/// left unbalanced by comment stripping or by the reduction to a skeleton.
+///
+/// With the `tree-sitter` feature, files with a grammar are parsed, and the first syntax error of
+/// `cleaned` is reported when `original` parses without errors. Otherwise, the check is a sanity
+/// check rather than a parse: brackets are matched outside of string and character literals,
+/// and fences are counted in markdown. Problems already present in the original file, including
+/// brackets within its comments, are not reported.
+///
+/// # Arguments
+/// * `original` - The content of the file as read.
+/// * `cleaned` - The content written to the prompt.
+/// * `extension` - The file extension indicating the language (e.g. "rs", "md").
+pub fn broken_by_cleaning(original: &str, cleaned: &str, extension: &str) -> Option<String> {
+    #[cfg(feature = "tree-sitter")]
+    if let Some(problem) = parse_error(original, cleaned, extension) {
+        return problem;
+    }
+    problem(cleaned, extension).filter(|_| problem(original, extension).is_none())
+}
+
+/// Parses `original` and `cleaned` with the grammar of `extension` and returns the first syntax
+/// error of `cleaned`, if any, or `None` when there is no grammar or `original` does not parse
+/// without errors either.
+#[cfg(feature = "tree-sitter")]
+fn parse_error(original: &str, cleaned: &str, extension: &str) -> Option<Option<String>> {
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(&crate::treesitter::language(extension)?).ok()?;
+    if parser.parse(original, None)?.root_node().has_error() {
+        return None;
+    }
+    let tree = parser.parse(cleaned, None)?;
+    Some(error_line(tree.root_node()).map(|line| format!("a syntax error on line {}", line)))
+}
+
+/// Returns the line of the first error or missing node under `node`.
+#[cfg(feature = "tree-sitter")]
+fn error_line(node: tree_sitter::Node) -> Option<usize> {
+    if !node.has_error() {
+        return None;
+    }
+    if node.is_error() || node.is_missing() {
+        return Some(node.start_position().row + 1);
+    }
+    let mut cursor = node.walk();
+    let line = node.children(&mut cursor).find_map(error_line);
+    Some(line.unwrap_or(node.start_position().row + 1))
+}
+
+/// Returns the first syntax problem found in `content`, or `None` when the language is not
+/// checked.
+fn problem(content: &str, extension: &str) -> Option<String> {
+    match extension {
+        "md" | "markdown" => unclosed_fence(content),
+        _ if BRACKET_LANGUAGES.contains(&extension) => unbalanced_bracket(content, SINGLE_QUOTE_STRINGS.contains(&extension)),
+        _ => None,
+    }
+}
+
+/// Reports the first bracket closed without being opened, or the innermost one left open.
+fn unbalanced_bracket(content: &str, single_quote_strings: bool) -> Option<String> {
+    let mut open: Vec<(char, usize)> = Vec::new();
+    let mut quote = None;
+    let mut line = 1;
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+        if let Some(delimiter) = quote {
+            if c == '\\' {
+                chars.next();
+            } else if c == delimiter {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' => quote = Some('"'),
+            '\'' if single_quote_strings => quote = Some('\''),
+            // A character literal such as '{' or '\n', while a lone quote is a Rust lifetime
+            '\'' => {
+                let mut rest = chars.clone();
+                let literal = match rest.next() {
+                    Some('\\') => rest.nth(1) == Some('\''),
+                    Some(_) => rest.next() == Some('\''),
+                    None => false,
+                };
+                if literal {
+                    quote = Some('\'');
+                }
+            }
+            '(' | '[' | '{' => open.push((c, line)),
+            ')' | ']' | '}' => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if open.pop().map(|(bracket, _)| bracket) != Some(expected) {
+                    return Some(format!("unbalanced `{}` on line {}", c, line));
+                }
+            }
+            _ => {}
+        }
+    }
+    open.pop().map(|(bracket, line)| format!("`{}` from line {} is never closed", bracket, line))
+}
+
+/// Reports a code fence opened and never closed. A fence is closed by a line made of at least as
+/// many of the same backticks or tildes.
+fn unclosed_fence(content: &str) -> Option<String> {
+    let mut opening: Option<(usize, char, usize)> = None;
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        let Some(marker) = line.chars().next().filter(|c| *c == '`' || *c == '~') else {
+            continue;
+        };
+        let length = line.len() - line.trim_start_matches(marker).len();
+        if length < 3 {
+            continue;
+        }
+        match opening {
+            None => opening = Some((i + 1, marker, length)),
+            Some((_, open_marker, open_length)) if marker == open_marker && length >= open_length && length == line.len() => opening = None,
+            Some(_) => {}
+        }
+    }
+    opening.map(|(line, _, _)| format!("the fence from line {} is never closed", line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broken_by_cleaning() {
+        let original = "fn main() {\n    let url = \"http://x/{\"; // url\n    let c = '}';\n    f::<'a>(x);\n}\n";
+        assert_eq!(broken_by_cleaning(original, original, "rs"), None);
+        assert_eq!(problem(original, "rs"), None);
+        assert_eq!(problem("fn main() {\n    let url = \"http:\n}\n", "rs"), Some("`{` from line 1 is never closed".to_string()));
+        assert_eq!(problem("fn main() }\n", "rs"), Some("unbalanced `}` on line 1".to_string()));
+        // Problems of the original file are not blamed on cleaning
+        assert_eq!(broken_by_cleaning("fn main() {\n", "fn main() {\n", "rs"), None);
+        assert_eq!(broken_by_cleaning("x = 'it''s {'\n", "x = 'it''s {'\n", "py"), None);
+
+        let markdown = "# Usage\n````md\n```bash\nrun\n```\n````\n";
+        assert_eq!(broken_by_cleaning(markdown, markdown, "md"), None);
+        assert_eq!(
+            broken_by_cleaning(markdown, "# Usage\n````md\n```bash\nrun\n```\n", "md"),
+            Some("the fence from line 2 is never closed".to_string())
+        );
+        assert_eq!(broken_by_cleaning("{", "}", "txt"), None);
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    #[test]
+    fn test_broken_by_cleaning_parse() {
+        // Balanced brackets do not make the cleaned code parse
+        let original = "fn main() {\n    let x = 1;\n}\n";
+        assert_eq!(broken_by_cleaning(original, original, "rs"), None);
+        assert_eq!(broken_by_cleaning(original, "fn main() {\n    let x = ;\n}\n", "rs"), Some("a syntax error on line 2".to_string()));
+        // When the original does not parse either, the brackets are checked instead
+        let broken = "fn main() {\n    let = ;\n}\n";
+        assert_eq!(broken_by_cleaning(broken, "fn main() {\n    let = ;\n", "rs"), Some("`{` from line 1 is never closed".to_string()));
+        assert_eq!(broken_by_cleaning(broken, broken, "rs"), None);
+    }
+}
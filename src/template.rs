@@ -0,0 +1,150 @@
+// src/template.rs
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Fills the `{{attachment "name"}}` slots of `text` (the introductory prompt or the goal)
+/// with the content of the matching attachments, as fenced blocks, and the `{{snippet "name"}}`
+/// slots with the matching snippets of the project, as is.
+///
+/// Other `{{...}}` expressions are left untouched.
+///
+/// # Arguments
+/// * `text` - The template text.
+/// * `attachments` - Attachment contents by name, from `--attach name=path`.
+/// * `snippets` - The `snippets` of the project configuration.
+/// * `used` - Receives the names of the attachments referenced by `text`.
+///
+/// # Returns
+/// The filled text, or an error naming the first slot without an attachment or snippet.
+pub fn render(
+    text: &str,
+    attachments: &BTreeMap<String, String>,
+    snippets: &BTreeMap<String, String>,
+    used: &mut BTreeSet<String>,
+) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start..].find("}}") else {
+            break;
+        };
+        let expression = &rest[start + 2..start + length];
+        result.push_str(&rest[..start]);
+        match (quoted_argument(expression, "attachment"), quoted_argument(expression, "snippet")) {
+            (Some(name), _) => {
+                let content = attachments.get(name).ok_or_else(|| {
+                    format!("the template requires the attachment \"{}\", provide it with --attach {}=PATH", name, name)
+                })?;
+                result.push_str(&format!("\n```\n{}\n```\n", content.trim_end()));
+                used.insert(name.to_string());
+            }
+            (None, Some(name)) => {
+                let snippet = snippets.get(name).ok_or_else(|| {
+                    format!("the template requires the snippet \"{}\", add it with prompt-gen snippet add {}", name, name)
+                })?;
+                result.push_str(snippet);
+            }
+            (None, None) => result.push_str(&rest[start..start + length + 2]),
+        }
+        rest = &rest[start + length + 2..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Returns the names of the `{name}` placeholders of a goal template, in order of first
+/// appearance. `{{...}}` slots are not placeholders.
+pub fn placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    for_each_placeholder(template, |name| {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+        None
+    });
+    names
+}
+
+/// Replaces the `{name}` placeholders of a goal template with their values. Placeholders
+/// without a value are left untouched.
+pub fn fill_placeholders(template: &str, values: &BTreeMap<String, String>) -> String {
+    for_each_placeholder(template, |name| values.get(name).map(String::as_str))
+}
+
+/// Calls `replace` with the name of each `{name}` placeholder of `template`, a name made of
+/// letters, digits, `_` and `-`, and returns the template with the placeholders replaced by
+/// the values returned.
+fn for_each_placeholder<'a, 'v>(template: &'a str, mut replace: impl FnMut(&'a str) -> Option<&'v str>) -> String {
+    let is_name = |name: &str| !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        // Keep `{{...}}` slots for `render`
+        if rest.starts_with("{{") {
+            let length = rest.find("}}").map_or(rest.len(), |end| end + 2);
+            result.push_str(&rest[..length]);
+            rest = &rest[length..];
+            continue;
+        }
+        match rest.find('}') {
+            Some(end) if is_name(&rest[1..end]) => {
+                result.push_str(replace(&rest[1..end]).unwrap_or(&rest[..=end]));
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                result.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Returns the name of a `function "name"` expression, such as `attachment "error_log"`.
+fn quoted_argument<'a>(expression: &'a str, function: &str) -> Option<&'a str> {
+    expression
+        .trim()
+        .strip_prefix(function)?
+        .trim()
+        .strip_prefix('"')?
+        .strip_suffix('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let attachments = BTreeMap::from([("error_log".to_string(), "panicked at main.rs:3\n".to_string())]);
+        let snippets = BTreeMap::from([("patch-format".to_string(), "Answer with a unified diff.".to_string())]);
+        let mut used = BTreeSet::new();
+
+        let rendered = render("Fix this crash:{{ attachment \"error_log\" }}Keep {{name}} as is.", &attachments, &snippets, &mut used).unwrap();
+        assert_eq!(rendered, "Fix this crash:\n```\npanicked at main.rs:3\n```\nKeep {{name}} as is.");
+        assert!(used.contains("error_log"));
+
+        let rendered = render("Fix it. {{snippet \"patch-format\"}}", &attachments, &snippets, &mut used).unwrap();
+        assert_eq!(rendered, "Fix it. Answer with a unified diff.");
+
+        let error = render("See {{attachment \"trace\"}}", &attachments, &snippets, &mut used).unwrap_err();
+        assert!(error.contains("--attach trace=PATH"));
+        let error = render("{{snippet \"style\"}}", &attachments, &snippets, &mut used).unwrap_err();
+        assert!(error.contains("prompt-gen snippet add style"));
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let template = "Fix the bug: {description}, seen in {file} {{snippet \"tests\"}} {not a name} {description}";
+        assert_eq!(placeholders(template), vec!["description", "file"]);
+
+        let values = BTreeMap::from([("description".to_string(), "the crash on empty input".to_string())]);
+        assert_eq!(
+            fill_placeholders(template, &values),
+            "Fix the bug: the crash on empty input, seen in {file} {{snippet \"tests\"}} {not a name} the crash on empty input"
+        );
+    }
+}
@@ -0,0 +1,54 @@
+// src/testing.rs
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// A directory for the files of a test, `prompt-gen-test-<name>-<pid>` in the system temporary
+/// directory. It is removed when dropped, including when the test fails.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates the directory, empty.
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("prompt-gen-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    /// Writes `content` to the file at `relative_path`, creating its parent directories, and
+    /// returns the path of the file.
+    pub fn write(&self, relative_path: &str, content: &str) -> PathBuf {
+        let path = self.0.join(relative_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl From<&TempDir> for PathBuf {
+    fn from(dir: &TempDir) -> Self {
+        dir.0.clone()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,291 @@
+// src/tokens.rs
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::atomic::AtomicFile;
+
+/// Texts shorter than this are tokenized every time: they are quick to count, and would mostly
+/// fill the cache with goals and file headers.
+const MIN_CACHED_BYTES: usize = 4096;
+
+/// Counts kept in the cache file when it grows larger than this, in addition to the counts used
+/// by the current run, which are always kept.
+const MAX_CACHED_COUNTS: usize = 100_000;
+
+/// The counts of the process, loaded from [`cache_path`] on first use.
+static COUNTS: OnceLock<Mutex<CountCache>> = OnceLock::new();
+
+/// Counts the tokens of a text, exactly for a given model family or approximately.
+pub trait Tokenizer {
+    /// Counts the tokens of `text`.
+    fn count(&self, text: &str) -> usize;
+}
+
+/// A fast estimate without any vocabulary, see [`estimate_tokens`]. Always available, so
+/// minimal builds still enforce token budgets.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Estimator;
+
+impl Tokenizer for Estimator {
+    fn count(&self, text: &str) -> usize {
+        estimate_tokens(text)
+    }
+}
+
+/// Exact counts with a BPE encoding of OpenAI models. The vocabularies are loaded on first use.
+/// Requires the `bpe` feature, on by default.
+#[cfg(feature = "bpe")]
+#[derive(Clone, Copy)]
+pub struct Bpe(&'static tiktoken_rs::CoreBPE);
+
+#[cfg(feature = "bpe")]
+impl Bpe {
+    /// The encoding of GPT-4 and GPT-3.5 models.
+    pub fn cl100k() -> Self {
+        Bpe(tiktoken_rs::cl100k_base_singleton())
+    }
+
+    /// The encoding of GPT-4o and later models.
+    pub fn o200k() -> Self {
+        Bpe(tiktoken_rs::o200k_base_singleton())
+    }
+}
+
+#[cfg(feature = "bpe")]
+impl Tokenizer for Bpe {
+    fn count(&self, text: &str) -> usize {
+        self.0.encode_ordinary(text).len()
+    }
+}
+
+/// The tokenizer selected by the `tokenizer` setting.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerKind {
+    /// The [`Estimator`].
+    #[default]
+    Estimate,
+    /// The BPE encoding of GPT-4 and GPT-3.5 models.
+    Cl100k,
+    /// The BPE encoding of GPT-4o and later models.
+    O200k,
+}
+
+impl TokenizerKind {
+    /// Returns the selected tokenizer. Without the `bpe` feature, the BPE encodings fall back
+    /// to the [`Estimator`] with a warning.
+    pub fn tokenizer(self) -> Box<dyn Tokenizer> {
+        match self {
+            TokenizerKind::Estimate => Box::new(Estimator),
+            #[cfg(feature = "bpe")]
+            TokenizerKind::Cl100k => Box::new(Bpe::cl100k()),
+            #[cfg(feature = "bpe")]
+            TokenizerKind::O200k => Box::new(Bpe::o200k()),
+            #[cfg(not(feature = "bpe"))]
+            TokenizerKind::Cl100k | TokenizerKind::O200k => {
+                static WARNING: std::sync::Once = std::sync::Once::new();
+                WARNING.call_once(|| eprintln!("Warning: built without the bpe feature, tokens are estimated instead of counted with {:?}.", self));
+                Box::new(Estimator)
+            }
+        }
+    }
+
+    /// Counts the tokens of `text` with the selected tokenizer. The counts of long texts with a
+    /// BPE encoding are cached by content hash, see [`save_counts`].
+    pub fn count(self, text: &str) -> usize {
+        let tokenizer = self.tokenizer();
+        // Estimating is about as fast as hashing the text to look its count up
+        if !cfg!(feature = "bpe") || self == TokenizerKind::Estimate || text.len() < MIN_CACHED_BYTES {
+            return tokenizer.count(text);
+        }
+        let counts = COUNTS.get_or_init(|| Mutex::new(cache_path().map(|path| CountCache::load(&path)).unwrap_or_default()));
+        let key = CountCache::key(self, text);
+        if let Some(count) = counts.lock().unwrap().get(&key) {
+            return count;
+        }
+        let count = tokenizer.count(text);
+        counts.lock().unwrap().insert(key, count);
+        count
+    }
+}
+
+/// Token counts keyed by tokenizer and content hash, so that the files and prompts left
+/// unchanged since an earlier run are not tokenized again.
+#[derive(Debug, Default)]
+struct CountCache {
+    counts: HashMap<String, usize>,
+    /// Keys looked up or added since the cache was loaded.
+    used: HashSet<String>,
+    /// Whether counts were added since the cache was loaded or saved.
+    changed: bool,
+    /// Counts looked up since the cache was loaded, and how many of them were found.
+    lookups: usize,
+    hits: usize,
+}
+
+impl CountCache {
+    /// Loads the counts saved at `path`, or starts empty when there are none or they cannot be
+    /// read.
+    fn load(path: &Path) -> Self {
+        let counts = fs::read(path).ok().and_then(|json| serde_json::from_slice(&json).ok()).unwrap_or_default();
+        CountCache { counts, ..Default::default() }
+    }
+
+    /// The key of `text` counted with `kind`: its CRC-32, its 64-bit FNV-1a hash and its length,
+    /// e.g. `cl100k-83899db0-a430d84680aabd0b-28`. The two independent hashes keep a collision,
+    /// which would silently reuse the count of another text, out of reach of a cache of this size.
+    fn key(kind: TokenizerKind, text: &str) -> String {
+        format!("{:?}-{:08x}-{:016x}-{}", kind, crc32fast::hash(text.as_bytes()), fnv1a(text.as_bytes()), text.len()).to_lowercase()
+    }
+
+    fn get(&mut self, key: &str) -> Option<usize> {
+        self.lookups += 1;
+        let count = *self.counts.get(key)?;
+        self.hits += 1;
+        self.used.insert(key.to_string());
+        Some(count)
+    }
+
+    fn insert(&mut self, key: String, count: usize) {
+        self.used.insert(key.clone());
+        self.counts.insert(key, count);
+        self.changed = true;
+    }
+
+    /// Writes the counts to `path` when counts were added, dropping those this run did not use
+    /// once there are more than [`MAX_CACHED_COUNTS`].
+    fn save(&mut self, path: &Path) -> io::Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        if self.counts.len() > MAX_CACHED_COUNTS {
+            let used = &self.used;
+            self.counts.retain(|key, _| used.contains(key));
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = AtomicFile::create(path)?;
+        serde_json::to_writer(&mut file, &self.counts)?;
+        file.commit()?;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+/// The 64-bit FNV-1a hash of `bytes`, stable across runs and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3))
+}
+
+/// `token-counts.json` in the `prompt-gen` directory of the platform cache directory.
+fn cache_path() -> Option<PathBuf> {
+    let suffix = std::env::var("CONFIG_TEST_SUFFIX").unwrap_or_default();
+    Some(dirs::cache_dir()?.join("prompt-gen").join(format!("token-counts{}.json", suffix)))
+}
+
+/// Saves the token counts added by this run for the next ones, warning on `status` when they
+/// cannot be written.
+pub fn save_counts(status: &mut dyn Write) -> io::Result<()> {
+    let (Some(counts), Some(path)) = (COUNTS.get(), cache_path()) else {
+        return Ok(());
+    };
+    if let Err(e) = counts.lock().unwrap().save(&path) {
+        writeln!(status, "Warning: could not save the token counts to {}: {}.", path.display(), e)?;
+    }
+    Ok(())
+}
+
+/// Returns how many token counts [`TokenizerKind::count`] found in the cache so far, and how
+/// many it looked up. Texts too short to be cached are not looked up.
+pub fn cache_usage() -> (usize, usize) {
+    COUNTS.get().map_or((0, 0), |counts| {
+        let counts = counts.lock().unwrap();
+        (counts.hits, counts.lookups)
+    })
+}
+
+/// Estimates the number of tokens in `text`.
+///
+/// Uses the common approximation of one token per four characters, which is
+/// close enough for English prose and source code to tell whether a prompt
+/// fits a model's context window. Chinese, Japanese and Korean characters,
+/// which BPE encodings rarely merge, count as one token each.
+///
+/// # Arguments
+/// * `text` - The text to measure.
+///
+/// # Returns
+/// The estimated token count, rounded up.
+pub fn estimate_tokens(text: &str) -> usize {
+    let cjk = text.chars().filter(|&c| is_cjk(c)).count();
+    cjk + (text.chars().count() - cjk).div_ceil(4)
+}
+
+/// Returns whether `c` is a CJK ideograph, kana or hangul syllable.
+fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30FF}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{AC00}'..='\u{D7AF}' | '\u{F900}'..='\u{FAFF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abc"), 1);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        // Characters are counted, not bytes
+        assert_eq!(estimate_tokens("éééé"), 1);
+        assert_eq!(estimate_tokens("// 日本語 ok"), 5);
+    }
+
+    #[test]
+    fn test_count_cache() {
+        let dir = crate::testing::TempDir::new("count-cache");
+        let path = dir.join("cache/token-counts.json");
+        let text = "fn main() {}\n".repeat(400);
+        let key = CountCache::key(TokenizerKind::Cl100k, &text);
+        assert_eq!(key, format!("cl100k-{:08x}-{:016x}-5200", crc32fast::hash(text.as_bytes()), fnv1a(text.as_bytes())));
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_ne!(CountCache::key(TokenizerKind::O200k, &text), key);
+
+        let mut cache = CountCache::load(&path);
+        assert_eq!(cache.get(&key), None);
+        assert_eq!((cache.hits, cache.lookups), (0, 1));
+        cache.insert(key.clone(), 1200);
+        cache.save(&path).unwrap();
+        assert!(!cache.changed);
+
+        // The next run finds the count, and saves nothing when it adds none
+        let mut cache = CountCache::load(&path);
+        assert_eq!(cache.get(&key), Some(1200));
+        assert_eq!((cache.hits, cache.lookups), (1, 1));
+        fs::remove_file(&path).unwrap();
+        cache.save(&path).unwrap();
+        assert!(!path.exists());
+
+        fs::write(&path, "not json").unwrap();
+        assert!(CountCache::load(&path).counts.is_empty());
+    }
+
+    #[test]
+    fn test_tokenizer_count() {
+        assert_eq!(TokenizerKind::Estimate.count("hello world"), 3);
+        assert_eq!(Estimator.count("hello world"), 3);
+        #[cfg(feature = "bpe")]
+        {
+            assert_eq!(TokenizerKind::Cl100k.count("hello world"), 2);
+            assert_eq!(Bpe::o200k().count(""), 0);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+// src/treesitter.rs
+
+use std::ops::Range;
+
+use tree_sitter::{Language, Node, Parser};
+
+/// A comment found by the grammar of a file.
+#[derive(Debug, PartialEq)]
+pub struct Comment {
+    /// Byte range of the comment in the file. Line comments end before their newline.
+    pub range: Range<usize>,
+    /// Whether it is a doc comment (`///`, `//!`, `/** */`, `/*! */`) or a Python docstring.
+    pub is_doc: bool,
+}
+
+/// Returns the tree-sitter grammar of the files with `extension`, if there is one.
+pub(crate) fn language(extension: &str) -> Option<Language> {
+    let language = match extension {
+        "rs" => tree_sitter_rust::LANGUAGE,
+        "c" | "h" => tree_sitter_c::LANGUAGE,
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" => tree_sitter_cpp::LANGUAGE,
+        "py" => tree_sitter_python::LANGUAGE,
+        "js" | "jsx" | "mjs" | "cjs" => tree_sitter_javascript::LANGUAGE,
+        "ts" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+        "tsx" => tree_sitter_typescript::LANGUAGE_TSX,
+        "go" => tree_sitter_go::LANGUAGE,
+        "java" => tree_sitter_java::LANGUAGE,
+        "sh" | "bash" | "zsh" => tree_sitter_bash::LANGUAGE,
+        "rb" => tree_sitter_ruby::LANGUAGE,
+        _ => return None,
+    };
+    Some(language.into())
+}
+
+/// Parses `source` with the grammar of `extension` and returns its comments in order, or
+/// `None` when there is no grammar for it. Comments are found as the grammar sees them, so
+/// comment markers inside strings, raw strings and nested comments are told apart; syntax
+/// errors only affect the code around them.
+pub fn comments(source: &str, extension: &str) -> Option<Vec<Comment>> {
+    let mut parser = Parser::new();
+    parser.set_language(&language(extension)?).ok()?;
+    let tree = parser.parse(source, None)?;
+
+    let mut comments = Vec::new();
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        let comment = comment(node, source);
+        let found = comment.is_some();
+        comments.extend(comment);
+        // The parts of a comment, such as the marker of a Rust doc comment, are not visited
+        if found || !cursor.goto_first_child() {
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return Some(comments);
+                }
+            }
+        }
+    }
+}
+
+/// Returns `node` as a comment, if it is one: a `comment`, `line_comment` or `block_comment`
+/// node, or a statement made of a triple-quoted string in Python.
+fn comment(node: Node, source: &str) -> Option<Comment> {
+    let text = &source[node.byte_range()];
+    if node.kind().ends_with("comment") {
+        let end = node.start_byte() + text.trim_end_matches(['\r', '\n']).len();
+        return Some(Comment {
+            range: node.start_byte()..end,
+            is_doc: is_doc_comment(text),
+        });
+    }
+    let is_docstring = node.kind() == "expression_statement"
+        && node.named_child_count() == 1
+        && node.named_child(0).is_some_and(|child| child.kind() == "string")
+        && (text.starts_with("\"\"\"") || text.starts_with("'''"));
+    is_docstring.then(|| Comment {
+        range: node.byte_range(),
+        is_doc: true,
+    })
+}
+
+/// Returns whether `text` is a doc comment; `////` and `/***` banners are not.
+fn is_doc_comment(text: &str) -> bool {
+    ["///", "//!", "/**", "/*!"]
+        .iter()
+        .any(|marker| text.strip_prefix(marker).is_some_and(|rest| !rest.starts_with(['/', '*'])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts<'a>(source: &'a str, extension: &str) -> Vec<(&'a str, bool)> {
+        comments(source, extension).unwrap().into_iter().map(|comment| (&source[comment.range], comment.is_doc)).collect()
+    }
+
+    #[test]
+    fn test_comments() {
+        let rust = "/// Runs.\nfn run() {\n    let url = \"https://example.com\"; // done\n    let raw = r#\"/* not */\"#;\n    /* outer /* nested */ still */\n}\n";
+        assert_eq!(texts(rust, "rs"), [("/// Runs.", true), ("// done", false), ("/* outer /* nested */ still */", false)]);
+
+        let python = "def f():\n    \"\"\"Docs.\"\"\"\n    return \"# not\"  # note\n";
+        assert_eq!(texts(python, "py"), [("\"\"\"Docs.\"\"\"", true), ("# note", false)]);
+
+        let shell = "echo $# \"#1\" # count\n";
+        assert_eq!(texts(shell, "sh"), [("# count", false)]);
+        assert_eq!(texts("//// banner\n/***/\nint x;\n", "c"), [("//// banner", false), ("/***/", false)]);
+        assert!(comments("# Notes", "md").is_none());
+    }
+}
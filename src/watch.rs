@@ -0,0 +1,202 @@
+// src/watch.rs
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+use notify::{PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use notify_debouncer_mini::{new_debouncer, new_debouncer_opt, DebounceEventResult, Debouncer};
+
+use crate::builder::FileCollector;
+use crate::config::Config;
+use crate::generate::{generate, GenerateOptions};
+
+/// How long the file events of a change, such as an editor saving several files, are gathered
+/// before the prompt is regenerated.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// The modification time of every file a prompt includes. Adding, removing or editing one of
+/// them changes the snapshot.
+#[derive(Debug, PartialEq, Eq)]
+struct Snapshot(BTreeMap<PathBuf, Option<SystemTime>>);
+
+impl Snapshot {
+    /// Walks the project like a prompt would, without reading the files.
+    fn take(config: &Config, project_dir: &Path) -> Self {
+        let files = FileCollector::new(config, project_dir).collect();
+        Snapshot(
+            files
+                .into_iter()
+                .map(|file| {
+                    let modified = fs::metadata(&file.source).and_then(|metadata| metadata.modified()).ok();
+                    (file.source, modified)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// The source of the file events of the project, kept alive while watching.
+enum FileEvents {
+    /// The file events of the platform: inotify, FSEvents, kqueue or ReadDirectoryChangesW.
+    Native(Debouncer<RecommendedWatcher>),
+    /// Modification times compared every interval, where file events are unavailable, e.g. on
+    /// network file systems or when the inotify watch limit is reached.
+    Polling(Debouncer<PollWatcher>),
+}
+
+impl FileEvents {
+    /// Starts watching `project_dir` recursively, sending the debounced events to `sender`.
+    /// Falls back to polling every `interval` when the file events of the platform fail.
+    fn start<W: Write>(project_dir: &Path, interval: Duration, sender: mpsc::Sender<DebounceEventResult>, status: &mut W) -> notify::Result<Self> {
+        let native = new_debouncer(DEBOUNCE, sender.clone()).map(FileEvents::Native).and_then(|mut events| {
+            events.watcher().watch(project_dir, RecursiveMode::Recursive)?;
+            Ok(events)
+        });
+        match native {
+            Ok(events) => Ok(events),
+            Err(e) => {
+                writeln!(status, "Warning: file events are unavailable ({}), checking the files every {:?} instead.", e, interval)?;
+                let config = notify_debouncer_mini::Config::default()
+                    .with_timeout(DEBOUNCE)
+                    .with_notify_config(notify::Config::default().with_poll_interval(interval));
+                let mut events = FileEvents::Polling(new_debouncer_opt(config, sender)?);
+                events.watcher().watch(project_dir, RecursiveMode::Recursive)?;
+                Ok(events)
+            }
+        }
+    }
+
+    fn watcher(&mut self) -> &mut dyn Watcher {
+        match self {
+            FileEvents::Native(debouncer) => debouncer.watcher(),
+            FileEvents::Polling(debouncer) => debouncer.watcher(),
+        }
+    }
+}
+
+/// Regenerates the prompt for `goal` whenever the files it includes change, as reported by the
+/// file events of the platform, or by checking them every `interval` where those are
+/// unavailable. The prompt goes through the same steps as [`generate`], and the prompt file is
+/// overwritten each time; this never returns unless generation fails.
+///
+/// # Arguments
+/// * `output` - Where to write the prompt, a file or a directory. Defaults to the configured output directory.
+/// * `status` - Receives a line for each regeneration.
+pub fn watch<W: Write>(
+    config: &Config,
+    project_dir: &Path,
+    goal: &str,
+    output: Option<&Path>,
+    interval: Duration,
+    status: &mut W,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (sender, changes) = mpsc::channel();
+    let _events = FileEvents::start(project_dir, interval, sender, status)?;
+    regenerate(config, project_dir, goal, output, changes, status)
+}
+
+/// Generates the prompt, then regenerates it for each batch of `changes` that adds, removes or
+/// modifies a file it includes, until the events stop. Events about other files, such as the
+/// prompt itself when written inside the project, are ignored.
+fn regenerate<W: Write>(
+    config: &Config,
+    project_dir: &Path,
+    goal: &str,
+    output: Option<&Path>,
+    changes: impl IntoIterator<Item = DebounceEventResult>,
+    status: &mut W,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut options = GenerateOptions::new(project_dir, goal).with_config(config.clone());
+    if let Some(output) = output {
+        options = options.with_output(output);
+    }
+    let mut last = None;
+    let mut changes = changes.into_iter();
+    loop {
+        let snapshot = Snapshot::take(config, project_dir);
+        if last.as_ref() != Some(&snapshot) {
+            let artifacts = generate(&options)?;
+            for warning in &artifacts.warnings {
+                writeln!(status, "{}", warning)?;
+            }
+            writeln!(
+                status,
+                "[{}] Prompt file generated: {} ({} files, ~{} tokens)",
+                chrono::Local::now().format("%H:%M:%S"),
+                artifacts.prompt_path.display(),
+                artifacts.files.len(),
+                artifacts.tokens
+            )?;
+            last = Some(snapshot);
+        }
+        match changes.next() {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => writeln!(status, "Warning: {}.", e)?,
+            None => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_snapshot() {
+        let project_dir = TempDir::new("watch");
+        project_dir.write("src/main.rs", "fn main() {}\n");
+        let config = Config {
+            allowed_extensions: vec!["rs".to_string()],
+            ..Default::default()
+        };
+
+        let snapshot = Snapshot::take(&config, &project_dir);
+        assert_eq!(Snapshot::take(&config, &project_dir), snapshot);
+        // Files the prompt leaves out do not count
+        project_dir.write("notes.md", "# Notes\n");
+        assert_eq!(Snapshot::take(&config, &project_dir), snapshot);
+        project_dir.write("src/lib.rs", "");
+        assert_ne!(Snapshot::take(&config, &project_dir), snapshot);
+    }
+
+    #[test]
+    fn test_regenerate() {
+        let project_dir = TempDir::new("watch-regenerate");
+        project_dir.write("src/main.rs", "fn main() {}\n");
+        let config = Config {
+            project_name: "app".to_string(),
+            intro_prompt: "{{snippet \"intro\"}}".to_string(),
+            allowed_extensions: vec!["rs".to_string()],
+            snippets: [("intro".to_string(), "Intro".to_string())].into(),
+            ..Default::default()
+        };
+        let output = project_dir.join("prompt.txt");
+
+        // Each batch of events is handled once the previous one is, like with a live watcher
+        let (sender, changes) = mpsc::sync_channel(0);
+        let mut status = Vec::new();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                // A file the prompt leaves out
+                project_dir.write("notes.md", "# Notes\n");
+                sender.send(Ok(Vec::new())).unwrap();
+                project_dir.write("src/lib.rs", "pub fn answer() -> u32 { 42 }\n");
+                sender.send(Ok(Vec::new())).unwrap();
+                drop(sender);
+            });
+            regenerate(&config, &project_dir, "Add a flag", Some(&output), changes, &mut status).unwrap();
+        });
+
+        let status = String::from_utf8(status).unwrap();
+        assert_eq!(status.matches("Prompt file generated").count(), 2, "{}", status);
+        assert!(status.contains("(2 files, ~"), "{}", status);
+        let prompt = fs::read_to_string(&output).unwrap();
+        assert!(prompt.starts_with("Intro\n"));
+        assert!(prompt.contains("pub fn answer() -> u32 { 42 }"));
+    }
+}